@@ -1,4 +1,7 @@
-use crate::databases::merge::Merge;
+use crate::databases::{
+    collection_command::CollectionCommand, convert::Convert, diff::Diff, merge::Merge,
+    playlist::Playlist, random_picker::Random, remap::Remap,
+};
 use crate::load_settings::{
     collection::collectiondb_load_settings::CollectionDbLoadSettings,
     osu::osudb_load_settings::OsuDbLoadSettings,
@@ -69,6 +72,14 @@ pub struct Arguments {
         default_value = "1"
     )]
     pub jobs: usize,
+    #[structopt(
+        name = "osu!.db path",
+        long = "osu-db",
+        value_name = "PATH",
+        help = "osu!.db to look up the beatmaps in collection.db in when displaying it, so they're \
+                shown by name rather than only by hash."
+    )]
+    pub osu_db_path: Option<String>,
     #[structopt(subcommand)]
     pub merge_search_or_interface: Option<MergeSearchOrInterface>,
 }
@@ -106,6 +117,18 @@ pub enum MergeSearchOrInterface {
     },
     #[structopt(name = "merge")]
     Merge(Merge),
+    #[structopt(name = "diff")]
+    Diff(Diff),
+    #[structopt(name = "convert")]
+    Convert(Convert),
+    #[structopt(name = "collection")]
+    Collection(CollectionCommand),
+    #[structopt(name = "remap")]
+    Remap(Remap),
+    #[structopt(name = "random")]
+    Random(Random),
+    #[structopt(name = "playlist")]
+    Playlist(Playlist),
     #[structopt(name = "interface")]
     Interface {
         #[structopt(
@@ -1,18 +1,26 @@
+use crate::databases::osu::beatmap::Beatmap;
 use crate::deserialize_primitives::*;
 use crate::read_error::ParseFileResult;
+use crate::serialize_primitives::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Beatmaps from osu!.db by MD5 hash, used to show what the hashes in a collection refer to.
+pub type BeatmapsByHash<'a, 'b> = HashMap<&'b str, &'b Beatmap<'a>>;
 
 /// The collection is the entry type in collection.db. Each entry has a name and the hashes of the
-/// beatmaps in a given collection.
+/// beatmaps in a given collection. The name is usually borrowed from the database file, but may be
+/// owned when it's been changed, e.g. by renaming a collection while merging.
 #[derive(Debug, Clone)]
 pub struct Collection<'a> {
-    pub collection_name: Option<&'a str>,
+    pub collection_name: Option<Cow<'a, str>>,
     pub number_of_beatmaps: i32,
     pub md5_beatmap_hashes: Vec<&'a str>,
 }
 
 impl<'a> Collection<'a> {
     pub fn read_from_bytes(bytes: &'a [u8], i: &mut usize) -> ParseFileResult<Self> {
-        let collection_name = read_str_utf8(bytes, i, "collection name")?;
+        let collection_name = read_str_utf8(bytes, i, "collection name")?.map(Cow::Borrowed);
         let number_of_beatmaps = read_int(bytes, i)?;
         let mut md5_beatmap_hashes = Vec::with_capacity(number_of_beatmaps as usize);
         for _ in 0..number_of_beatmaps {
@@ -25,7 +33,19 @@ impl<'a> Collection<'a> {
         })
     }
 
-    pub fn display(&self) {
+    /// Write the collection to a buffer. The number of beatmaps is taken from `md5_beatmap_hashes`
+    /// rather than `number_of_beatmaps`.
+    pub fn write_to_bytes(&self, bytes: &mut Vec<u8>) {
+        write_str_utf8(self.collection_name.as_deref(), bytes);
+        write_int(self.md5_beatmap_hashes.len() as i32, bytes);
+        for &hash in &self.md5_beatmap_hashes {
+            write_md5_hash(hash, bytes);
+        }
+    }
+
+    /// Display the collection. With `beatmaps`, each hash is followed by the beatmap it belongs to,
+    /// or marked as missing if there's none.
+    pub fn display(&self, beatmaps: Option<&BeatmapsByHash>) {
         if self.collection_name.is_some() {
             println!(
                 "    collection name: {}",
@@ -37,8 +57,18 @@ impl<'a> Collection<'a> {
         println!("    number of beatmaps: {}", self.number_of_beatmaps);
         println!("    Hashes {{");
         for &hash in &self.md5_beatmap_hashes {
-            println!("        {}", hash);
+            println!("        {}", hash_label(hash, beatmaps));
         }
         println!("    }}");
     }
 }
+
+/// The hash, followed by "Artist - Title [Difficulty] (creator)" and the beatmap and set IDs of the
+/// beatmap in `beatmaps` it belongs to, if `beatmaps` is given.
+pub fn hash_label(hash: &str, beatmaps: Option<&BeatmapsByHash>) -> String {
+    match beatmaps.map(|beatmaps| beatmaps.get(hash)) {
+        None => hash.to_string(),
+        Some(None) => format!("{} (missing from osu!.db)", hash),
+        Some(Some(beatmap)) => format!("{} {}", hash, beatmap.label()),
+    }
+}
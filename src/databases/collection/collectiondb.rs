@@ -1,7 +1,9 @@
-use crate::databases::collection::collection::Collection;
+use crate::databases::collection::collection::{BeatmapsByHash, Collection};
 use crate::deserialize_primitives::*;
 use crate::read_error::{DbFileParseError, ParseErrorKind, ParseFileResult};
+use crate::serialize_primitives::*;
 use crossbeam_utils::thread::{self, Scope, ScopedJoinHandle};
+use std::borrow::Cow;
 use std::sync::{Arc, Mutex};
 
 /// Contains collections of beatmaps defined by the user, typically in the game. This can be used
@@ -106,12 +108,24 @@ impl<'a> CollectionDb<'a> {
         })
     }
 
-    pub fn display(&self) {
+    /// Write the database to a buffer. The number of collections is taken from `collections`
+    /// rather than `number_of_collections`.
+    pub fn write_to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_int(self.version, &mut bytes);
+        write_int(self.collections.len() as i32, &mut bytes);
+        for collection in &self.collections {
+            collection.write_to_bytes(&mut bytes);
+        }
+        bytes
+    }
+
+    pub fn display(&self, beatmaps: Option<&BeatmapsByHash>) {
         println!("version: {}", self.version);
         println!("number of collections: {}", self.number_of_collections);
         println!("collections {{");
         for collection in &self.collections {
-            collection.display();
+            collection.display(beatmaps);
         }
         println!("}}");
     }
@@ -136,7 +150,8 @@ fn spawn_collection_loader_thread<'scope, 'b: 'scope, 'a: 'b>(
                 }
                 let num = *ctr - 1;
                 let mut start = start_read.lock().unwrap();
-                let collection_name = read_str_utf8(bytes, &mut *start, "collection name")?;
+                let collection_name =
+                    read_str_utf8(bytes, &mut *start, "collection name")?.map(Cow::Borrowed);
                 let number_of_beatmaps = read_int(bytes, &mut *start)?;
                 let s = *start;
                 // Accounts for: 1 indicator byte, 1 length byte, and 32 bytes for MD5 hash.
@@ -159,3 +174,68 @@ fn spawn_collection_loader_thread<'scope, 'b: 'scope, 'a: 'b>(
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASHES: [&str; 3] = [
+        "0123456789abcdef0123456789abcdef",
+        "fedcba9876543210fedcba9876543210",
+        "00000000000000000000000000000000",
+    ];
+
+    fn collection_db() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_int(20211231, &mut bytes);
+        write_int(2, &mut bytes);
+        write_str_utf8(Some("Streams"), &mut bytes);
+        write_int(2, &mut bytes);
+        write_md5_hash(HASHES[0], &mut bytes);
+        write_md5_hash(HASHES[1], &mut bytes);
+        write_str_utf8(Some("Empty"), &mut bytes);
+        write_int(0, &mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn collection_db_round_trips() {
+        let bytes = collection_db();
+        for &jobs in &[1, 2] {
+            let collection_db = CollectionDb::read_from_bytes(jobs, &bytes).unwrap();
+            assert_eq!(collection_db.write_to_bytes(), bytes, "{} jobs", jobs);
+        }
+    }
+
+    #[test]
+    fn counts_are_recomputed_when_written() {
+        let bytes = collection_db();
+        let mut collection_db = CollectionDb::read_from_bytes(1, &bytes).unwrap();
+        // Edit the collections without touching any of the counts.
+        collection_db.collections[0].md5_beatmap_hashes.remove(0);
+        collection_db.collections[1]
+            .md5_beatmap_hashes
+            .extend_from_slice(&HASHES);
+        collection_db.collections.push(Collection {
+            collection_name: Some(Cow::Borrowed("Jumps")),
+            number_of_beatmaps: 0,
+            md5_beatmap_hashes: vec![HASHES[2]],
+        });
+        let written = collection_db.write_to_bytes();
+        let read_back = CollectionDb::read_from_bytes(1, &written).unwrap();
+        assert_eq!(read_back.number_of_collections, 3);
+        let counts = read_back
+            .collections
+            .iter()
+            .map(|collection| collection.number_of_beatmaps)
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [1, 3, 1]);
+        let names = read_back
+            .collections
+            .iter()
+            .map(|collection| collection.collection_name.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(names, [Some("Streams"), Some("Empty"), Some("Jumps")]);
+        assert_eq!(read_back.collections[1].md5_beatmap_hashes, HASHES);
+    }
+}
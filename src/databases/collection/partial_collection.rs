@@ -1,8 +1,9 @@
+use crate::databases::collection::collection::{hash_label, BeatmapsByHash};
 use crate::deserialize_primitives::*;
 use crate::load_settings::collection::collection_load_settings::CollectionLoadSettings;
 use crate::maybe_deserialize_primitives::*;
 use crate::read_error::ParseFileResult;
-use crate::{masks::collection_mask::CollectionMask, maybe_print, maybe_print_vec};
+use crate::{masks::collection_mask::CollectionMask, maybe_print};
 
 #[derive(Debug, Clone)]
 pub struct PartialCollection<'a> {
@@ -64,9 +65,17 @@ impl<'a> PartialCollection<'a> {
         }
     }
 
-    pub fn display(&self, show: CollectionMask) {
+    pub fn display(&self, show: CollectionMask, beatmaps: Option<&BeatmapsByHash>) {
         maybe_print!(show.collection_name, self.collection_name);
         maybe_print!(show.number_of_beatmaps, self.number_of_beatmaps);
-        maybe_print_vec!(show.md5_beatmap_hashes, self.md5_beatmap_hashes, "hashes");
+        if show.md5_beatmap_hashes {
+            if let Some(hashes) = &self.md5_beatmap_hashes {
+                println!("hashes {{");
+                for hash in hashes {
+                    println!("    {}", hash_label(hash, beatmaps));
+                }
+                println!("}}");
+            }
+        }
     }
 }
@@ -1,4 +1,6 @@
-use crate::databases::collection::partial_collection::PartialCollection;
+use crate::databases::collection::{
+    collection::BeatmapsByHash, partial_collection::PartialCollection,
+};
 use crate::deserialize_primitives::*;
 use crate::load_settings::collection::{
     collection_load_settings::CollectionLoadSettings,
@@ -136,13 +138,13 @@ impl<'a> PartialCollectionDb<'a> {
         })
     }
 
-    pub fn display(&self, show: CollectionDbMask) {
+    pub fn display(&self, show: CollectionDbMask, beatmaps: Option<&BeatmapsByHash>) {
         if !show.ignore_all() {
             maybe_print!(show.version, self.version);
             maybe_print!(show.number_of_collections, self.number_of_collections);
             if self.collections.is_some() && !show.collections_mask.ignore_all() {
                 for collection in self.collections.as_ref().unwrap() {
-                    collection.display(show.collections_mask);
+                    collection.display(show.collections_mask, beatmaps);
                 }
             }
         }
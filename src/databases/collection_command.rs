@@ -0,0 +1,481 @@
+use crate::argument::DbIndicator;
+use crate::databases::{
+    collection::{collection::Collection, collectiondb::CollectionDb},
+    dangling::Dangling,
+    id_list::IdList,
+    osdb::{Export, Import},
+    osu::osudb::OsuDb,
+    smart_collection::Smart,
+};
+use crate::load_settings::osu::beatmap_load_settings::BeatmapLoadSettings;
+use crate::output::{write_atomically, Backups};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct CollectionCommand {
+    #[structopt(flatten)]
+    backups: Backups,
+    #[structopt(subcommand)]
+    action: CollectionAction,
+}
+
+#[derive(StructOpt)]
+pub enum CollectionAction {
+    #[structopt(name = "create")]
+    Create {
+        #[structopt(value_name = "NAME")]
+        name: String,
+    },
+    #[structopt(name = "rename")]
+    Rename {
+        #[structopt(value_name = "NAME")]
+        name: String,
+        #[structopt(value_name = "NEW-NAME")]
+        new_name: String,
+    },
+    #[structopt(name = "delete")]
+    Delete {
+        #[structopt(value_name = "NAME")]
+        name: String,
+    },
+    #[structopt(name = "add")]
+    Add {
+        #[structopt(value_name = "NAME")]
+        name: String,
+        #[structopt(flatten)]
+        beatmaps: BeatmapSelection,
+    },
+    #[structopt(name = "remove")]
+    Remove {
+        #[structopt(value_name = "NAME")]
+        name: String,
+        #[structopt(flatten)]
+        beatmaps: BeatmapSelection,
+    },
+    #[structopt(name = "from-ids")]
+    FromIds {
+        #[structopt(value_name = "NAME")]
+        name: String,
+        #[structopt(flatten)]
+        ids: IdList,
+    },
+    #[structopt(name = "smart")]
+    Smart(Smart),
+    #[structopt(name = "import")]
+    Import(Import),
+    #[structopt(name = "export")]
+    Export(Export),
+    #[structopt(name = "dangling")]
+    Dangling(Dangling),
+    #[structopt(name = "combine")]
+    Combine {
+        #[structopt(
+            value_name = "OPERATION",
+            possible_values(&["union", "intersection", "difference", "symmetric-difference"]),
+            parse(try_from_str)
+        )]
+        operation: SetOperation,
+        #[structopt(value_name = "NAME", required = true, min_values = 2)]
+        names: Vec<String>,
+        #[structopt(long = "into", value_name = "NEW-NAME")]
+        into: String,
+        #[structopt(
+            long = "preview",
+            help = "List the beatmaps the new collection would have without creating it."
+        )]
+        preview: bool,
+    },
+}
+
+/// Ways of combining the beatmaps of several collections. Beatmaps keep the order they first
+/// appear in, going through the collections in the order they're given.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SetOperation {
+    /// Beatmaps in any of the collections.
+    Union,
+    /// Beatmaps in every one of the collections.
+    Intersection,
+    /// Beatmaps in the first collection but none of the others.
+    Difference,
+    /// Beatmaps in exactly one of the collections.
+    SymmetricDifference,
+}
+
+impl FromStr for SetOperation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "union" => Ok(SetOperation::Union),
+            "intersection" => Ok(SetOperation::Intersection),
+            "difference" => Ok(SetOperation::Difference),
+            "symmetric-difference" => Ok(SetOperation::SymmetricDifference),
+            _ => Err(format!("Unknown set operation: {}", s)),
+        }
+    }
+}
+
+impl SetOperation {
+    pub fn apply<'a>(self, collections: &[&[&'a str]]) -> Vec<&'a str> {
+        let sets = collections
+            .iter()
+            .map(|hashes| hashes.iter().copied().collect::<HashSet<_>>())
+            .collect::<Vec<_>>();
+        let count = |hash: &str| sets.iter().filter(|set| set.contains(hash)).count();
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+        for (i, hashes) in collections.iter().enumerate() {
+            for &hash in hashes.iter() {
+                if !seen.insert(hash) {
+                    continue;
+                }
+                let keep = match self {
+                    SetOperation::Union => true,
+                    SetOperation::Intersection => count(hash) == sets.len(),
+                    SetOperation::Difference => i == 0 && count(hash) == 1,
+                    SetOperation::SymmetricDifference => count(hash) == 1,
+                };
+                if keep {
+                    result.push(hash);
+                }
+            }
+        }
+        result
+    }
+}
+
+// Beatmaps given either directly by their MD5 hashes, or as the beatmaps in an osu!.db matching a
+// query, or both.
+#[derive(StructOpt)]
+pub struct BeatmapSelection {
+    #[structopt(value_name = "MD5")]
+    md5_beatmap_hashes: Vec<String>,
+    #[structopt(
+        name = "osu!.db path",
+        long = "osu-db",
+        value_name = "PATH",
+        help = "osu!.db to look up beatmaps matching the query in"
+    )]
+    osu_db_path: Option<String>,
+    #[structopt(flatten)]
+    query: BeatmapLoadSettings,
+}
+
+impl BeatmapSelection {
+    /// Collect the selected hashes, or explain why they can't be.
+    pub fn resolve(&self, jobs: usize) -> Result<Vec<String>, String> {
+        let mut hashes = Vec::new();
+        for hash in &self.md5_beatmap_hashes {
+            if !is_md5_hash(hash) {
+                return Err(format!("Not an MD5 beatmap hash: {}", hash));
+            }
+            hashes.push(hash.to_lowercase());
+        }
+        match (&self.osu_db_path, self.query.ignore_all()) {
+            (Some(path), false) => {
+                let buffer = read_osu_db(path);
+                let osu_db =
+                    OsuDb::read_from_bytes(jobs, &buffer).expect("Failed to parse osu!.db.");
+                hashes.extend(
+                    osu_db
+                        .beatmaps
+                        .iter()
+                        .filter(|beatmap| self.query.matches(beatmap))
+                        .map(|beatmap| beatmap.md5_beatmap_hash.to_string()),
+                );
+            }
+            (Some(_), true) => return Err("No query given to look up in osu!.db.".into()),
+            (None, false) => return Err("Querying beatmaps requires --osu-db.".into()),
+            (None, true) if hashes.is_empty() => {
+                return Err("No beatmaps given. Pass MD5 hashes or a query with --osu-db.".into())
+            }
+            (None, true) => {}
+        }
+        Ok(hashes)
+    }
+}
+
+pub(crate) fn read_osu_db(path: &str) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    let mut file = File::open(path).expect("Failed to open osu!.db file.");
+    file.read_to_end(&mut buffer)
+        .expect("Failed to read osu!.db file.");
+    buffer
+}
+
+impl CollectionCommand {
+    /// Apply the action to the collection.db in `bytes` and save it back to `db_path`.
+    pub fn run(&self, jobs: usize, db_type: DbIndicator, db_path: &str, bytes: &[u8]) {
+        if db_type != DbIndicator::CollectionDb {
+            println!("Collection commands only work on collection.db.");
+            return;
+        }
+        match &self.action {
+            CollectionAction::Smart(smart) => {
+                smart.run(jobs, db_path, bytes, self.backups.count);
+                return;
+            }
+            CollectionAction::Import(import) => {
+                import.run(jobs, db_path, bytes, self.backups.count);
+                return;
+            }
+            CollectionAction::Export(export) => {
+                export.run(jobs, bytes);
+                return;
+            }
+            CollectionAction::Dangling(dangling) => {
+                dangling.run(jobs, db_path, bytes, self.backups.count);
+                return;
+            }
+            _ => {}
+        }
+        let hashes = match self.action.resolve_hashes(jobs) {
+            Some(Ok(hashes)) => hashes,
+            Some(Err(msg)) => {
+                println!("{}", msg);
+                return;
+            }
+            None => Vec::new(),
+        };
+        let mut collection_db =
+            CollectionDb::read_from_bytes(jobs, bytes).expect("Failed to parse database.");
+        match self.action.apply(&mut collection_db, &hashes) {
+            Ok(msg) if self.action.is_preview() => println!("{}", msg),
+            Ok(msg) => {
+                write_atomically(
+                    Path::new(db_path),
+                    &collection_db.write_to_bytes(),
+                    self.backups.count,
+                )
+                .expect("Failed to write collection database.");
+                println!("{}", msg);
+            }
+            Err(msg) => println!("{}", msg),
+        }
+    }
+}
+
+impl CollectionAction {
+    /// Look up the hashes of the beatmaps the action is given, if it's given any.
+    fn resolve_hashes(&self, jobs: usize) -> Option<Result<Vec<String>, String>> {
+        match self {
+            CollectionAction::Add { beatmaps, .. } | CollectionAction::Remove { beatmaps, .. } => {
+                Some(beatmaps.resolve(jobs))
+            }
+            CollectionAction::FromIds { ids, .. } => Some(ids.resolve(jobs)),
+            _ => None,
+        }
+    }
+
+    fn is_preview(&self) -> bool {
+        match self {
+            CollectionAction::Combine { preview, .. } => *preview,
+            _ => false,
+        }
+    }
+
+    /// Apply the action, returning a message describing what was done, or why it couldn't be.
+    /// `hashes` are the hashes resolved from the beatmaps the action is given, if it's given any.
+    pub fn apply<'a>(
+        &self,
+        collection_db: &mut CollectionDb<'a>,
+        hashes: &'a [String],
+    ) -> Result<String, String> {
+        match self {
+            CollectionAction::Create { name } => {
+                if find_collection(collection_db, name).is_some() {
+                    return Err(format!("Collection \"{}\" already exists.", name));
+                }
+                collection_db.collections.push(Collection {
+                    collection_name: Some(Cow::Owned(name.clone())),
+                    number_of_beatmaps: 0,
+                    md5_beatmap_hashes: Vec::new(),
+                });
+                collection_db.number_of_collections = collection_db.collections.len() as i32;
+                Ok(format!("Created collection \"{}\".", name))
+            }
+            CollectionAction::FromIds { name, .. } => {
+                if find_collection(collection_db, name).is_some() {
+                    return Err(format!("Collection \"{}\" already exists.", name));
+                }
+                collection_db.collections.push(Collection {
+                    collection_name: Some(Cow::Owned(name.clone())),
+                    number_of_beatmaps: hashes.len() as i32,
+                    md5_beatmap_hashes: hashes.iter().map(String::as_str).collect(),
+                });
+                collection_db.number_of_collections = collection_db.collections.len() as i32;
+                Ok(format!(
+                    "Created collection \"{}\" with {} beatmaps.",
+                    name,
+                    hashes.len()
+                ))
+            }
+            CollectionAction::Rename { name, new_name } => {
+                let index = find_collection_or_err(collection_db, name)?;
+                if name == new_name {
+                    // Nothing to change, so don't rewrite the file or rotate a backup.
+                    return Err(format!("Collection \"{}\" already has that name.", name));
+                }
+                if find_collection(collection_db, new_name).is_some() {
+                    return Err(format!("Collection \"{}\" already exists.", new_name));
+                }
+                collection_db.collections[index].collection_name =
+                    Some(Cow::Owned(new_name.clone()));
+                Ok(format!(
+                    "Renamed collection \"{}\" to \"{}\".",
+                    name, new_name
+                ))
+            }
+            CollectionAction::Delete { name } => {
+                let index = find_collection_or_err(collection_db, name)?;
+                let collection = collection_db.collections.remove(index);
+                collection_db.number_of_collections = collection_db.collections.len() as i32;
+                Ok(format!(
+                    "Deleted collection \"{}\" ({} beatmaps).",
+                    name,
+                    collection.md5_beatmap_hashes.len()
+                ))
+            }
+            CollectionAction::Add { name, .. } => {
+                let index = find_collection_or_err(collection_db, name)?;
+                let collection = &mut collection_db.collections[index];
+                let before = collection.md5_beatmap_hashes.len();
+                for hash in hashes {
+                    if !collection.md5_beatmap_hashes.contains(&hash.as_str()) {
+                        collection.md5_beatmap_hashes.push(hash);
+                    }
+                }
+                let added = collection.md5_beatmap_hashes.len() - before;
+                collection.number_of_beatmaps = collection.md5_beatmap_hashes.len() as i32;
+                Ok(format!(
+                    "Added {} beatmaps to collection \"{}\" ({} were already in it).",
+                    added,
+                    name,
+                    hashes.len() - added
+                ))
+            }
+            CollectionAction::Remove { name, .. } => {
+                let index = find_collection_or_err(collection_db, name)?;
+                let collection = &mut collection_db.collections[index];
+                let before = collection.md5_beatmap_hashes.len();
+                collection
+                    .md5_beatmap_hashes
+                    .retain(|hash| !hashes.iter().any(|removed| removed == hash));
+                let removed = before - collection.md5_beatmap_hashes.len();
+                collection.number_of_beatmaps = collection.md5_beatmap_hashes.len() as i32;
+                Ok(format!(
+                    "Removed {} beatmaps from collection \"{}\".",
+                    removed, name
+                ))
+            }
+            CollectionAction::Combine {
+                operation,
+                names,
+                into,
+                preview,
+            } => {
+                if find_collection(collection_db, into).is_some() {
+                    return Err(format!("Collection \"{}\" already exists.", into));
+                }
+                let mut collections = Vec::with_capacity(names.len());
+                for name in names {
+                    let index = find_collection_or_err(collection_db, name)?;
+                    collections.push(&collection_db.collections[index].md5_beatmap_hashes[..]);
+                }
+                let combined = operation.apply(&collections);
+                if *preview {
+                    let mut msg = format!(
+                        "Collection \"{}\" would have {} beatmaps:",
+                        into,
+                        combined.len()
+                    );
+                    for hash in &combined {
+                        msg.push_str("\n  ");
+                        msg.push_str(hash);
+                    }
+                    return Ok(msg);
+                }
+                let number_of_beatmaps = combined.len();
+                collection_db.collections.push(Collection {
+                    collection_name: Some(Cow::Owned(into.clone())),
+                    number_of_beatmaps: number_of_beatmaps as i32,
+                    md5_beatmap_hashes: combined,
+                });
+                collection_db.number_of_collections = collection_db.collections.len() as i32;
+                Ok(format!(
+                    "Created collection \"{}\" with {} beatmaps.",
+                    into, number_of_beatmaps
+                ))
+            }
+            // These read another database alongside collection.db, which has to outlive it, or
+            // don't change collection.db at all, so `run` handles them separately.
+            CollectionAction::Smart(_)
+            | CollectionAction::Import(_)
+            | CollectionAction::Export(_)
+            | CollectionAction::Dangling(_) => {
+                Err("This action can't be applied to collection.db on its own.".into())
+            }
+        }
+    }
+}
+
+/// Whether `hash` looks like an MD5 beatmap hash, which is 32 hexadecimal digits.
+pub(crate) fn is_md5_hash(hash: &str) -> bool {
+    hash.len() == 32 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Save `hashes` to the collection called `name` in the collection.db at `path`, creating it if it
+/// doesn't exist and replacing its beatmaps if it does.
+pub(crate) fn save_to_collection(
+    jobs: usize,
+    path: &str,
+    name: &str,
+    hashes: Vec<&str>,
+    backups: usize,
+) {
+    let mut buffer = Vec::new();
+    let mut file = File::open(path).expect("Failed to open collection.db file.");
+    file.read_to_end(&mut buffer)
+        .expect("Failed to read collection.db file.");
+    let mut collection_db =
+        CollectionDb::read_from_bytes(jobs, &buffer).expect("Failed to parse collection.db.");
+    let collection = Collection {
+        collection_name: Some(Cow::Owned(name.to_string())),
+        number_of_beatmaps: hashes.len() as i32,
+        md5_beatmap_hashes: hashes,
+    };
+    let verb = match find_collection(&collection_db, name) {
+        Some(index) => {
+            collection_db.collections[index] = collection;
+            "Replaced"
+        }
+        None => {
+            collection_db.collections.push(collection);
+            collection_db.number_of_collections = collection_db.collections.len() as i32;
+            "Created"
+        }
+    };
+    write_atomically(Path::new(path), &collection_db.write_to_bytes(), backups)
+        .expect("Failed to write collection database.");
+    println!("{} collection \"{}\" in {}", verb, name, path);
+}
+
+pub(crate) fn find_collection(collection_db: &CollectionDb, name: &str) -> Option<usize> {
+    collection_db
+        .collections
+        .iter()
+        .position(|collection| collection.collection_name.as_deref() == Some(name))
+}
+
+pub(crate) fn find_collection_or_err(
+    collection_db: &CollectionDb,
+    name: &str,
+) -> Result<usize, String> {
+    find_collection(collection_db, name).ok_or_else(|| format!("No collection named \"{}\".", name))
+}
@@ -0,0 +1,208 @@
+use crate::argument::DbIndicator;
+use crate::databases::osu::{
+    osudb::OsuDb,
+    primitives::{ByteSingle, UnknownShortOrUserPermissions, UserPermissions},
+    versions::Modern,
+};
+use crate::output::Output;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct Convert {
+    #[structopt(
+        name = "target version",
+        long = "to-version",
+        value_name = "VERSION",
+        help = "Version to convert to. Versions before 20140609 use the legacy format, versions \
+                before 20160408 the modern format, versions before 20191107 the modern format \
+                with entry sizes, and everything after the modern format with user permissions."
+    )]
+    target_version: i32,
+    #[structopt(flatten)]
+    output: Output,
+}
+
+impl Convert {
+    /// Convert the database in `bytes` to `--to-version` and write the result to `--output`. Only
+    /// osu!.db has more than one layout, so it's the only database that can be converted.
+    pub fn convert(&self, jobs: usize, db_type: DbIndicator, bytes: &[u8]) {
+        if db_type != DbIndicator::OsuDb {
+            println!("Converting {:?} not currently supported.", db_type);
+            return;
+        }
+        if !self.output.can_write() {
+            return;
+        }
+        let mut osu_db = OsuDb::read_from_bytes(jobs, bytes).expect("Failed to parse database.");
+        let from_version = osu_db.version;
+        for warning in convert_osu_db(&mut osu_db, self.target_version) {
+            println!("warning: {}", warning);
+        }
+        let converted = osu_db.write_to_bytes();
+        self.output
+            .write(&converted)
+            .expect("Failed to write converted database.");
+        println!(
+            "Converted osu!.db from version {} to version {} and wrote it to {}",
+            from_version, self.target_version, self.output.path
+        );
+    }
+}
+
+/// The osu!.db layouts, in the order they were introduced. Each one corresponds to one of the
+/// unit structs in `databases::osu::versions`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Era {
+    Legacy,
+    Modern,
+    ModernWithEntrySize,
+    ModernWithPermissions,
+}
+
+impl Era {
+    fn of(version: i32) -> Self {
+        if version < 20140609 {
+            Era::Legacy
+        } else if version < 20160408 {
+            Era::Modern
+        } else if version < 20191107 {
+            Era::ModernWithEntrySize
+        } else {
+            Era::ModernWithPermissions
+        }
+    }
+}
+
+/// Convert `osu_db` in place so that it can be written as `version`, returning a warning for every
+/// kind of data that couldn't be carried over. Fields that only exist in the target layout are
+/// filled in with the values osu! itself writes for them.
+pub fn convert_osu_db(osu_db: &mut OsuDb, version: i32) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let from = Era::of(osu_db.version);
+    let to = Era::of(version);
+    osu_db.version = version;
+    if from == to {
+        return warnings;
+    }
+    let mut rounded_arcshpod = 0;
+    let mut dropped_star_ratings = 0;
+    let mut dropped_unknown_shorts = 0;
+    for beatmap in &mut osu_db.beatmaps {
+        let difficulty_values = [
+            &mut beatmap.approach_rate,
+            &mut beatmap.circle_size,
+            &mut beatmap.hp_drain,
+            &mut beatmap.overall_difficulty,
+        ];
+        let mut rounded = false;
+        for value in difficulty_values {
+            *value = match (*value, to) {
+                (ByteSingle::Single(single), Era::Legacy) => {
+                    let byte = single.round().clamp(0.0, 255.0);
+                    rounded |= byte != single;
+                    ByteSingle::Byte(byte as u8)
+                }
+                (ByteSingle::Byte(byte), to) if to != Era::Legacy => {
+                    ByteSingle::Single(byte as f32)
+                }
+                (value, _) => value,
+            };
+        }
+        if rounded {
+            rounded_arcshpod += 1;
+        }
+        let star_ratings = [
+            (
+                &mut beatmap.num_mod_combo_star_ratings_standard,
+                &mut beatmap.mod_combo_star_ratings_standard,
+            ),
+            (
+                &mut beatmap.num_mod_combo_star_ratings_taiko,
+                &mut beatmap.mod_combo_star_ratings_taiko,
+            ),
+            (
+                &mut beatmap.num_mod_combo_star_ratings_ctb,
+                &mut beatmap.mod_combo_star_ratings_ctb,
+            ),
+            (
+                &mut beatmap.num_mod_combo_star_ratings_mania,
+                &mut beatmap.mod_combo_star_ratings_mania,
+            ),
+        ];
+        let mut dropped = false;
+        for (number, ratings) in star_ratings {
+            if to == Era::Legacy {
+                dropped |= matches!(ratings, Some(ratings) if !ratings.is_empty());
+                *number = None;
+                *ratings = None;
+            } else if ratings.is_none() {
+                *number = Some(0);
+                *ratings = Some(Vec::new());
+            }
+        }
+        if dropped {
+            dropped_star_ratings += 1;
+        }
+        if to == Era::Legacy {
+            beatmap.unknown_short = beatmap.unknown_short.or(Some(0));
+        } else if let Some(unknown_short) = beatmap.unknown_short.take() {
+            if unknown_short != 0 {
+                dropped_unknown_shorts += 1;
+            }
+        }
+        beatmap.entry_size = None;
+        if to == Era::ModernWithEntrySize {
+            // The entry size doesn't count itself, so it's the size of the entry as written by the
+            // layout before it.
+            let mut entry = Vec::new();
+            beatmap.write_to_bytes::<Modern>(&mut entry);
+            beatmap.entry_size = Some(entry.len() as i32);
+        }
+    }
+    if rounded_arcshpod > 0 {
+        warnings.push(format!(
+            "{} beatmaps had an AR, CS, HP, or OD that isn't a whole number between 0 and 255. \
+             Those values were rounded.",
+            rounded_arcshpod
+        ));
+    }
+    if dropped_star_ratings > 0 {
+        warnings.push(format!(
+            "Dropped the precalculated star ratings of {} beatmaps, which aren't stored before \
+             version 20140609.",
+            dropped_star_ratings
+        ));
+    }
+    if dropped_unknown_shorts > 0 {
+        warnings.push(format!(
+            "Dropped the unknown short of {} beatmaps, which isn't stored after version 20140609.",
+            dropped_unknown_shorts
+        ));
+    }
+    osu_db.unknown_short_or_permissions = match (osu_db.unknown_short_or_permissions, to) {
+        (UnknownShortOrUserPermissions::UserPermissions(permissions), to)
+            if to != Era::ModernWithPermissions =>
+        {
+            if permissions != UserPermissions::None {
+                warnings.push(format!(
+                    "Dropped the user permissions ({:?}), which aren't stored before version \
+                     20191107.",
+                    permissions
+                ));
+            }
+            UnknownShortOrUserPermissions::UnknownShort(0)
+        }
+        (UnknownShortOrUserPermissions::UnknownShort(short), Era::ModernWithPermissions) => {
+            if short != 0 {
+                warnings.push(format!(
+                    "Dropped the unknown short at the end of the file ({}), which isn't stored \
+                     after version 20191107.",
+                    short
+                ));
+            }
+            UnknownShortOrUserPermissions::UserPermissions(UserPermissions::None)
+        }
+        (value, _) => value,
+    };
+    warnings
+}
@@ -0,0 +1,83 @@
+use crate::databases::{
+    collection::collectiondb::CollectionDb, collection_command::read_osu_db, osu::osudb::OsuDb,
+};
+use crate::output::write_atomically;
+use std::collections::HashSet;
+use std::path::Path;
+use structopt::StructOpt;
+
+// Hashes in collections that no beatmap in osu!.db has, usually left behind by beatmaps that were
+// deleted or updated. osu! still counts them towards a collection's size.
+#[derive(StructOpt)]
+pub struct Dangling {
+    #[structopt(
+        name = "osu!.db path",
+        long = "osu-db",
+        value_name = "PATH",
+        help = "osu!.db to check the collections' beatmaps against"
+    )]
+    osu_db_path: String,
+    #[structopt(
+        long = "remove",
+        help = "Remove the dangling hashes and save collection.db, instead of only listing them."
+    )]
+    remove: bool,
+}
+
+impl Dangling {
+    /// List the dangling hashes in the collection.db in `bytes` by collection, and with `--remove`,
+    /// save it back to `db_path` without them.
+    pub fn run(&self, jobs: usize, db_path: &str, bytes: &[u8], backups: usize) {
+        let osu_buffer = read_osu_db(&self.osu_db_path);
+        let osu_db = OsuDb::read_from_bytes(jobs, &osu_buffer).expect("Failed to parse osu!.db.");
+        let installed = osu_db
+            .beatmaps
+            .iter()
+            .map(|beatmap| beatmap.md5_beatmap_hash)
+            .collect::<HashSet<_>>();
+        let mut collection_db =
+            CollectionDb::read_from_bytes(jobs, bytes).expect("Failed to parse database.");
+        let mut total = 0;
+        for collection in &mut collection_db.collections {
+            let dangling = collection
+                .md5_beatmap_hashes
+                .iter()
+                .filter(|hash| !installed.contains(*hash))
+                .copied()
+                .collect::<Vec<_>>();
+            if dangling.is_empty() {
+                continue;
+            }
+            println!(
+                "Collection \"{}\": {} of {} beatmaps dangling",
+                collection.collection_name.as_deref().unwrap_or(""),
+                dangling.len(),
+                collection.md5_beatmap_hashes.len()
+            );
+            for hash in &dangling {
+                println!("  {}", hash);
+            }
+            total += dangling.len();
+            if self.remove {
+                collection
+                    .md5_beatmap_hashes
+                    .retain(|hash| installed.contains(hash));
+                collection.number_of_beatmaps = collection.md5_beatmap_hashes.len() as i32;
+            }
+        }
+        if total == 0 {
+            println!("No dangling hashes.");
+            return;
+        }
+        if self.remove {
+            write_atomically(Path::new(db_path), &collection_db.write_to_bytes(), backups)
+                .expect("Failed to write collection database.");
+            println!("Removed {} dangling hashes.", total);
+        } else {
+            println!(
+                "{} dangling hashes in total. Use --remove to remove them.",
+                total
+            );
+        }
+    }
+}
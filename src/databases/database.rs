@@ -1,6 +1,9 @@
 use crate::argument::DbIndicator;
 use crate::databases::{
-    collection::{collectiondb::CollectionDb, partial_collectiondb::PartialCollectionDb},
+    collection::{
+        collection::BeatmapsByHash, collectiondb::CollectionDb,
+        partial_collectiondb::PartialCollectionDb,
+    },
     osu::{osudb::OsuDb, partial_osudb::PartialOsuDb},
     scores::{partial_scoresdb::PartialScoresDb, scoresdb::ScoresDb},
 };
@@ -47,14 +50,16 @@ impl<'a> OsuDatabase<'a> {
         })
     }
 
-    pub fn display(&self, show: Option<DbMask>) {
+    /// Display the database, limited to `show` for partial databases. `beatmaps` are only used by
+    /// collection.db, to show the beatmaps its hashes belong to.
+    pub fn display(&self, show: Option<DbMask>, beatmaps: Option<&BeatmapsByHash>) {
         match (self, show) {
             (Osu(osudb), None) => osudb.display(),
-            (Collection(collectiondb), None) => collectiondb.display(),
+            (Collection(collectiondb), None) => collectiondb.display(beatmaps),
             (Scores(scoresdb), None) => scoresdb.display(),
             (PartialOsu(partialosudb), Some(DbMask::OsuMask(mask))) => partialosudb.display(mask),
             (PartialCollection(partialcollectiondb), Some(DbMask::CollectionMask(mask))) => {
-                partialcollectiondb.display(mask)
+                partialcollectiondb.display(mask, beatmaps)
             }
             (PartialScores(partialscoresdb), Some(DbMask::ScoresMask(mask))) => {
                 partialscoresdb.display(mask)
@@ -0,0 +1,300 @@
+use crate::argument::DbIndicator;
+use crate::databases::{
+    collection::{collection::Collection, collectiondb::CollectionDb},
+    database::OsuDatabase,
+    merge::{score_label, ScoreKeys},
+    osu::{beatmap::Beatmap, osudb::OsuDb},
+    scores::{score::Score, scoresdb::ScoresDb},
+};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct Diff {
+    #[structopt(short = "w", long = "with", value_name = "PATH")]
+    other_path: String,
+}
+
+impl Diff {
+    /// Compare the database in `bytes` to the one at `--with`, treating the latter as the newer of
+    /// the two.
+    pub fn diff(&self, jobs: usize, db_type: DbIndicator, bytes: &[u8]) {
+        let mut other_buffer = Vec::new();
+        let mut file =
+            File::open(&self.other_path).expect("Failed to open database file to compare.");
+        file.read_to_end(&mut other_buffer)
+            .expect("Failed to read database file to compare.");
+        let database =
+            OsuDatabase::read_from_bytes(jobs, db_type, bytes).expect("Failed to parse database.");
+        let other_database = OsuDatabase::read_from_bytes(jobs, db_type, &other_buffer)
+            .expect("Failed to parse database to compare.");
+        match (database, other_database) {
+            (OsuDatabase::Osu(old), OsuDatabase::Osu(new)) => diff_osu_dbs(&old, &new),
+            (OsuDatabase::Collection(old), OsuDatabase::Collection(new)) => {
+                diff_collection_dbs(&old, &new)
+            }
+            (OsuDatabase::Scores(old), OsuDatabase::Scores(new)) => diff_scores_dbs(&old, &new),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Collects the fields that differ between two entries as `(field name, old value, new value)`.
+macro_rules! changed_fields {
+    ($old:expr, $new:expr, $($field:ident),* $(,)?) => {{
+        let mut changes = Vec::new();
+        $(
+            if $old.$field != $new.$field {
+                changes.push((
+                    stringify!($field),
+                    format!("{:?}", $old.$field),
+                    format!("{:?}", $new.$field),
+                ));
+            }
+        )*
+        changes
+    }};
+}
+
+fn beatmap_label(beatmap: &Beatmap) -> String {
+    format!(
+        "{} {} - {} [{}]",
+        beatmap.md5_beatmap_hash,
+        beatmap.artist_name.unwrap_or(""),
+        beatmap.song_title.unwrap_or(""),
+        beatmap.difficulty.unwrap_or("")
+    )
+}
+
+/// Lists the beatmaps added, removed, or changed, matching them up by their MD5 hash. The entry size
+/// isn't compared since it only changes along with other fields.
+pub fn diff_osu_dbs(old: &OsuDb, new: &OsuDb) {
+    if old.version != new.version {
+        println!("version: {} -> {}", old.version, new.version);
+    }
+    let old_beatmaps = old
+        .beatmaps
+        .iter()
+        .map(|beatmap| (beatmap.md5_beatmap_hash, beatmap))
+        .collect::<HashMap<_, _>>();
+    let new_beatmaps = new
+        .beatmaps
+        .iter()
+        .map(|beatmap| (beatmap.md5_beatmap_hash, beatmap))
+        .collect::<HashMap<_, _>>();
+    let added = new
+        .beatmaps
+        .iter()
+        .filter(|beatmap| !old_beatmaps.contains_key(beatmap.md5_beatmap_hash))
+        .collect::<Vec<_>>();
+    let removed = old
+        .beatmaps
+        .iter()
+        .filter(|beatmap| !new_beatmaps.contains_key(beatmap.md5_beatmap_hash))
+        .collect::<Vec<_>>();
+    let changed = old
+        .beatmaps
+        .iter()
+        .filter_map(|old_beatmap| {
+            let new_beatmap = new_beatmaps.get(old_beatmap.md5_beatmap_hash)?;
+            let changes = changed_fields!(
+                old_beatmap,
+                new_beatmap,
+                artist_name,
+                artist_name_unicode,
+                song_title,
+                song_title_unicode,
+                creator_name,
+                difficulty,
+                audio_file_name,
+                dotosu_file_name,
+                ranked_status,
+                number_of_hitcircles,
+                number_of_sliders,
+                number_of_spinners,
+                last_modification_time,
+                approach_rate,
+                circle_size,
+                hp_drain,
+                overall_difficulty,
+                slider_velocity,
+                mod_combo_star_ratings_standard,
+                mod_combo_star_ratings_taiko,
+                mod_combo_star_ratings_ctb,
+                mod_combo_star_ratings_mania,
+                drain_time,
+                total_time,
+                preview_offset_from_start_ms,
+                timing_points,
+                beatmap_id,
+                beatmap_set_id,
+                thread_id,
+                standard_grade,
+                taiko_grade,
+                ctb_grade,
+                mania_grade,
+                local_offset,
+                stack_leniency,
+                gameplay_mode,
+                song_source,
+                song_tags,
+                online_offset,
+                font_used_for_song_title,
+                unplayed,
+                last_played,
+                is_osz2,
+                beatmap_folder_name,
+                last_checked_against_repo,
+                ignore_beatmap_sound,
+                ignore_beatmap_skin,
+                disable_storyboard,
+                disable_video,
+                visual_override,
+                unknown_short,
+                offset_from_song_start_in_editor_ms,
+                mania_scroll_speed,
+            );
+            if changes.is_empty() {
+                None
+            } else {
+                Some((old_beatmap, changes))
+            }
+        })
+        .collect::<Vec<_>>();
+    println!("beatmaps added ({}) {{", added.len());
+    for beatmap in added {
+        println!("    + {}", beatmap_label(beatmap));
+    }
+    println!("}}");
+    println!("beatmaps removed ({}) {{", removed.len());
+    for beatmap in removed {
+        println!("    - {}", beatmap_label(beatmap));
+    }
+    println!("}}");
+    println!("beatmaps changed ({}) {{", changed.len());
+    for (beatmap, changes) in changed {
+        println!("    ~ {}", beatmap_label(beatmap));
+        for (field, old_value, new_value) in changes {
+            println!("        {}: {} -> {}", field, old_value, new_value);
+        }
+    }
+    println!("}}");
+}
+
+fn collection_name(collection: &Collection) -> String {
+    format!(
+        "\"{}\"",
+        collection.collection_name.as_deref().unwrap_or("")
+    )
+}
+
+/// Lists the collections added or removed, matching them up by name, along with the beatmaps added
+/// to or removed from each collection present in both.
+pub fn diff_collection_dbs(old: &CollectionDb, new: &CollectionDb) {
+    if old.version != new.version {
+        println!("version: {} -> {}", old.version, new.version);
+    }
+    for old_collection in &old.collections {
+        let new_collection = new
+            .collections
+            .iter()
+            .find(|collection| collection.collection_name == old_collection.collection_name);
+        let new_collection = match new_collection {
+            Some(new_collection) => new_collection,
+            None => {
+                println!(
+                    "- collection {} ({} beatmaps)",
+                    collection_name(old_collection),
+                    old_collection.md5_beatmap_hashes.len()
+                );
+                continue;
+            }
+        };
+        let old_hashes = old_collection
+            .md5_beatmap_hashes
+            .iter()
+            .collect::<HashSet<_>>();
+        let new_hashes = new_collection
+            .md5_beatmap_hashes
+            .iter()
+            .collect::<HashSet<_>>();
+        if old_hashes == new_hashes {
+            continue;
+        }
+        println!("~ collection {}", collection_name(old_collection));
+        let mut printed = HashSet::new();
+        for hash in &new_collection.md5_beatmap_hashes {
+            if !old_hashes.contains(hash) && printed.insert(hash) {
+                println!("    + {}", hash);
+            }
+        }
+        for hash in &old_collection.md5_beatmap_hashes {
+            if !new_hashes.contains(hash) && printed.insert(hash) {
+                println!("    - {}", hash);
+            }
+        }
+    }
+    for new_collection in &new.collections {
+        let existed = old
+            .collections
+            .iter()
+            .any(|collection| collection.collection_name == new_collection.collection_name);
+        if !existed {
+            println!(
+                "+ collection {} ({} beatmaps)",
+                collection_name(new_collection),
+                new_collection.md5_beatmap_hashes.len()
+            );
+            for hash in &new_collection.md5_beatmap_hashes {
+                println!("    + {}", hash);
+            }
+        }
+    }
+}
+
+fn all_scores<'a, 'b>(scores_db: &'b ScoresDb<'a>) -> Vec<&'b Score<'a>> {
+    scores_db
+        .beatmaps
+        .iter()
+        .flat_map(|beatmap| beatmap.scores.iter().flatten())
+        .collect()
+}
+
+/// Lists the scores added or removed. Scores are matched up the same way as when merging.
+pub fn diff_scores_dbs(old: &ScoresDb, new: &ScoresDb) {
+    if old.version != new.version {
+        println!("version: {} -> {}", old.version, new.version);
+    }
+    let old_scores = all_scores(old);
+    let new_scores = all_scores(new);
+    let old_keys = ScoreKeys::new(old_scores.iter().copied());
+    let new_keys = ScoreKeys::new(new_scores.iter().copied());
+    let added = new_scores
+        .iter()
+        .filter(|score| !old_keys.contains(score))
+        .collect::<Vec<_>>();
+    let removed = old_scores
+        .iter()
+        .filter(|score| !new_keys.contains(score))
+        .collect::<Vec<_>>();
+    println!("scores added ({}) {{", added.len());
+    for score in added {
+        println!(
+            "    + {} ({} points)",
+            score_label(score),
+            score.replay_score
+        );
+    }
+    println!("}}");
+    println!("scores removed ({}) {{", removed.len());
+    for score in removed {
+        println!(
+            "    - {} ({} points)",
+            score_label(score),
+            score.replay_score
+        );
+    }
+    println!("}}");
+}
@@ -0,0 +1,116 @@
+use crate::databases::{collection_command::read_osu_db, osu::osudb::OsuDb};
+use std::collections::HashMap;
+use std::fs;
+use structopt::StructOpt;
+
+// Beatmaps given by their beatmap IDs or beatmap set IDs in a plain text or CSV file, such as a
+// tournament mappool or a beatmap pack listing.
+#[derive(StructOpt)]
+pub struct IdList {
+    #[structopt(
+        value_name = "ID-FILE",
+        help = "File with one ID per line. In CSV files, the first field that's a number is used, \
+                unless --column is given. Lines without an ID, like headers, are skipped, as are \
+                lines starting with #."
+    )]
+    path: String,
+    #[structopt(
+        name = "osu!.db path",
+        long = "osu-db",
+        value_name = "PATH",
+        help = "osu!.db to look up the IDs in"
+    )]
+    osu_db_path: String,
+    #[structopt(
+        long = "sets",
+        help = "The IDs are beatmap set IDs, so every difficulty in each set is added."
+    )]
+    sets: bool,
+    #[structopt(
+        long = "column",
+        value_name = "NUM",
+        help = "Take the ID from this column, counting from 1."
+    )]
+    column: Option<usize>,
+}
+
+impl IdList {
+    /// Look up the hashes of the listed beatmaps, in the order they're listed, and print the IDs
+    /// that aren't in osu!.db.
+    pub fn resolve(&self, jobs: usize) -> Result<Vec<String>, String> {
+        if self.column == Some(0) {
+            return Err("Columns are counted from 1.".into());
+        }
+        let ids = self.read_ids()?;
+        let buffer = read_osu_db(&self.osu_db_path);
+        let osu_db = OsuDb::read_from_bytes(jobs, &buffer).expect("Failed to parse osu!.db.");
+        let mut hashes_by_id: HashMap<i32, Vec<&str>> = HashMap::new();
+        for beatmap in &osu_db.beatmaps {
+            let id = if self.sets {
+                beatmap.beatmap_set_id
+            } else {
+                beatmap.beatmap_id
+            };
+            hashes_by_id
+                .entry(id)
+                .or_default()
+                .push(beatmap.md5_beatmap_hash);
+        }
+        let kind = if self.sets { "Beatmap set" } else { "Beatmap" };
+        let mut hashes = Vec::new();
+        let mut missing = 0;
+        for &id in &ids {
+            match hashes_by_id.get(&id) {
+                Some(id_hashes) => {
+                    for &hash in id_hashes {
+                        if !hashes.iter().any(|added| added == hash) {
+                            hashes.push(hash.to_string());
+                        }
+                    }
+                }
+                None => {
+                    println!("{} ID {} isn't installed.", kind, id);
+                    missing += 1;
+                }
+            }
+        }
+        println!(
+            "{} of {} IDs installed, {} beatmaps found.",
+            ids.len() - missing,
+            ids.len(),
+            hashes.len()
+        );
+        Ok(hashes)
+    }
+
+    fn read_ids(&self) -> Result<Vec<i32>, String> {
+        let contents = fs::read_to_string(&self.path).expect("Failed to read ID file.");
+        let mut ids = Vec::new();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line
+                .split(&[',', ';', '\t'][..])
+                .map(|field| field.trim().trim_matches('"'));
+            let id = match self.column {
+                Some(column) => fields.nth(column - 1).and_then(parse_id),
+                None => fields.find_map(parse_id),
+            };
+            if let Some(id) = id {
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+        if ids.is_empty() {
+            return Err(format!("No IDs found in {}.", self.path));
+        }
+        Ok(ids)
+    }
+}
+
+fn parse_id(field: &str) -> Option<i32> {
+    field.parse::<i32>().ok().filter(|&id| id > 0)
+}
@@ -1,11 +1,1027 @@
+use crate::argument::DbIndicator;
+use crate::databases::{
+    collection::{collection::Collection, collectiondb::CollectionDb},
+    scores::{score::Score, scoresdb::ScoresDb, scoresdb_beatmap::ScoresDbBeatmap},
+};
+use crate::output::Output;
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::Read;
+use std::str::FromStr;
 use structopt::StructOpt;
 
 #[derive(StructOpt)]
 pub struct Merge {
-    #[structopt(short = "m", long = "merge-with", value_name = "PATH")]
-    merge_with: String,
-    #[structopt(short = "o", long = "output", value_name = "PATH")]
-    output_path: String,
-    #[structopt(long = "overwrite-output")]
-    overwrite_output: bool,
+    #[structopt(
+        short = "m",
+        long = "merge-with",
+        value_name = "PATH",
+        required = true,
+        min_values = 1
+    )]
+    merge_with: Vec<String>,
+    #[structopt(flatten)]
+    output: Output,
+    #[structopt(long = "base", value_name = "PATH")]
+    base: Option<String>,
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+    #[structopt(long = "json")]
+    json: bool,
+    #[structopt(
+        name = "conflict policy",
+        long = "on-conflict",
+        value_name = "POLICY",
+        possible_values(&["keep-both", "prefer-left", "prefer-right", "prefer-newer", "rename"]),
+        default_value = "keep-both",
+        parse(try_from_str)
+    )]
+    on_conflict: ConflictPolicy,
+}
+
+impl Merge {
+    /// Merge the database in `bytes` with the ones at `--merge-with` and write the result to
+    /// `--output`. The databases are merged one after the other in the order they're given, so
+    /// for each of them, "left" is everything merged so far.
+    pub fn merge(&self, jobs: usize, db_type: DbIndicator, bytes: &[u8]) {
+        if !self.dry_run && !self.output.can_write() {
+            return;
+        }
+        let other_buffers = self
+            .merge_with
+            .iter()
+            .map(|path| {
+                let mut buffer = Vec::new();
+                let mut file = File::open(path).expect("Failed to open database file to merge.");
+                file.read_to_end(&mut buffer)
+                    .expect("Failed to read database file to merge.");
+                buffer
+            })
+            .collect::<Vec<_>>();
+        let base_buffer = self.base.as_ref().map(|path| {
+            let mut buffer = Vec::new();
+            let mut file = File::open(path).expect("Failed to open base database file.");
+            file.read_to_end(&mut buffer)
+                .expect("Failed to read base database file.");
+            buffer
+        });
+        let mut summary = MergeSummary::default();
+        let merged = match db_type {
+            DbIndicator::CollectionDb => {
+                let base = base_buffer.as_ref().map(|base_buffer| {
+                    CollectionDb::read_from_bytes(jobs, base_buffer)
+                        .expect("Failed to parse base database.")
+                });
+                let mut collection_db =
+                    CollectionDb::read_from_bytes(jobs, bytes).expect("Failed to parse database.");
+                for (path, other_buffer) in self.merge_with.iter().zip(&other_buffers) {
+                    let other_collection_db = CollectionDb::read_from_bytes(jobs, other_buffer)
+                        .expect("Failed to parse database to merge.");
+                    let merged = if let Some(base) = base.as_ref() {
+                        three_way_merge_collection_dbs(
+                            collection_db,
+                            base,
+                            other_collection_db,
+                            self.on_conflict,
+                            path,
+                            &mut summary,
+                        )
+                    } else {
+                        merge_collection_dbs(
+                            collection_db,
+                            other_collection_db,
+                            self.on_conflict,
+                            path,
+                            &mut summary,
+                        )
+                    };
+                    collection_db = match merged {
+                        Ok(collection_db) => collection_db,
+                        Err(msg) => {
+                            println!("{}", msg);
+                            return;
+                        }
+                    };
+                }
+                collection_db.write_to_bytes()
+            }
+            DbIndicator::ScoresDb if self.base.is_some() => {
+                println!("Three-way merging with --base is only supported for collection.db.");
+                return;
+            }
+            DbIndicator::ScoresDb => {
+                let mut scores_db =
+                    ScoresDb::read_from_bytes(jobs, bytes).expect("Failed to parse database.");
+                for (path, other_buffer) in self.merge_with.iter().zip(&other_buffers) {
+                    let other_scores_db = ScoresDb::read_from_bytes(jobs, other_buffer)
+                        .expect("Failed to parse database to merge.");
+                    scores_db = match merge_scores_dbs(
+                        scores_db,
+                        other_scores_db,
+                        self.on_conflict,
+                        path,
+                        &mut summary,
+                    ) {
+                        Ok(scores_db) => scores_db,
+                        Err(msg) => {
+                            println!("{}", msg);
+                            return;
+                        }
+                    };
+                }
+                scores_db.write_to_bytes()
+            }
+            _ => {
+                println!("Merging {:?} not currently supported.", db_type);
+                return;
+            }
+        };
+        if self.dry_run {
+            if self.json {
+                println!("{}", summary.to_json());
+            } else {
+                print!("{}", summary);
+                println!("Dry run, so nothing was written to {}", self.output.path);
+            }
+            return;
+        }
+        self.output
+            .write(&merged)
+            .expect("Failed to write merged database.");
+        if self.json {
+            println!("{}", summary.to_json());
+        } else {
+            print!("{}", summary);
+            println!("Wrote merged database to {}", self.output.path);
+        }
+    }
+}
+
+/// What to do when both databases have their own version of the same entry. "Left" is the
+/// database given with `--path` and "right" is the one being merged in from `--merge-with`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ConflictPolicy {
+    /// Union the hashes of clashing collections. Scores can't have two copies with the same replay
+    /// hash, so for them this keeps the left score.
+    KeepBoth,
+    PreferLeft,
+    PreferRight,
+    /// Only for scores, which are compared by `replay_timestamp`.
+    PreferNewer,
+    /// Only for collections. The right collection is added under a new name, e.g. "Streams (2)".
+    Rename,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "keep-both" => Ok(ConflictPolicy::KeepBoth),
+            "prefer-left" => Ok(ConflictPolicy::PreferLeft),
+            "prefer-right" => Ok(ConflictPolicy::PreferRight),
+            "prefer-newer" => Ok(ConflictPolicy::PreferNewer),
+            "rename" => Ok(ConflictPolicy::Rename),
+            _ => Err(format!("Unknown conflict policy: {}", s)),
+        }
+    }
+}
+
+/// A single choice made while merging, e.g. which of two clashing collections was kept. `source`
+/// is the path of the database that was being merged in, and `hashes_added` holds the beatmaps
+/// that were added to a collection, if any.
+#[derive(Clone, Debug)]
+pub struct MergeDecision {
+    pub source: String,
+    pub subject: String,
+    pub outcome: String,
+    pub conflict: bool,
+    pub hashes_added: Vec<String>,
+}
+
+/// Every decision made during a merge, in the order they were made, along with running totals.
+#[derive(Clone, Debug, Default)]
+pub struct MergeSummary {
+    pub decisions: Vec<MergeDecision>,
+    pub collections_added: usize,
+    pub collections_extended: usize,
+    pub hashes_added: usize,
+    pub scores_added: usize,
+    pub duplicate_scores_skipped: usize,
+    pub conflicts: usize,
+}
+
+impl MergeSummary {
+    fn record<S: Into<String>, O: Into<String>>(
+        &mut self,
+        source: &str,
+        subject: S,
+        outcome: O,
+    ) -> &mut MergeDecision {
+        self.decisions.push(MergeDecision {
+            source: source.to_string(),
+            subject: subject.into(),
+            outcome: outcome.into(),
+            conflict: false,
+            hashes_added: Vec::new(),
+        });
+        self.decisions.last_mut().unwrap()
+    }
+
+    fn record_conflict<S: Into<String>, O: Into<String>>(
+        &mut self,
+        source: &str,
+        subject: S,
+        outcome: O,
+    ) -> &mut MergeDecision {
+        self.conflicts += 1;
+        let decision = self.record(source, subject, outcome);
+        decision.conflict = true;
+        decision
+    }
+
+    fn record_hashes_added(
+        &mut self,
+        source: &str,
+        subject: String,
+        outcome: String,
+        hashes: &[&str],
+    ) {
+        self.hashes_added += hashes.len();
+        self.record(source, subject, outcome).hashes_added =
+            hashes.iter().map(|hash| hash.to_string()).collect();
+    }
+
+    /// The summary as a single JSON object, for when the merge is being reviewed by something other
+    /// than a person.
+    pub fn to_json(&self) -> String {
+        let decisions = self
+            .decisions
+            .iter()
+            .map(|decision| {
+                format!(
+                    "{{\"source\":{},\"subject\":{},\"outcome\":{},\"conflict\":{},\
+                     \"hashes_added\":[{}]}}",
+                    json_string(&decision.source),
+                    json_string(&decision.subject),
+                    json_string(&decision.outcome),
+                    decision.conflict,
+                    decision
+                        .hashes_added
+                        .iter()
+                        .map(|hash| json_string(hash))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "{{\"collections_added\":{},\"collections_extended\":{},\"hashes_added\":{},\
+             \"scores_added\":{},\"duplicate_scores_skipped\":{},\"conflicts\":{},\
+             \"decisions\":[{}]}}",
+            self.collections_added,
+            self.collections_extended,
+            self.hashes_added,
+            self.scores_added,
+            self.duplicate_scores_skipped,
+            self.conflicts,
+            decisions.join(",")
+        )
+    }
+}
+
+impl Display for MergeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.decisions.is_empty() {
+            return writeln!(f, "Merge summary: no changes.");
+        }
+        writeln!(f, "Merge summary:")?;
+        let mut source = None;
+        for decision in &self.decisions {
+            if source != Some(&decision.source) {
+                writeln!(f, "  merging {}:", decision.source)?;
+                source = Some(&decision.source);
+            }
+            let conflict = if decision.conflict { "conflict: " } else { "" };
+            writeln!(
+                f,
+                "    {}{}: {}",
+                conflict, decision.subject, decision.outcome
+            )?;
+        }
+        writeln!(
+            f,
+            "{} collections added, {} collections extended, {} beatmap hashes added, {} scores \
+             added, {} duplicate scores skipped, {} conflicts",
+            self.collections_added,
+            self.collections_extended,
+            self.hashes_added,
+            self.scores_added,
+            self.duplicate_scores_skipped,
+            self.conflicts
+        )
+    }
+}
+
+/// Quotes and escapes a string for use in JSON.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Merges `other` into `collection_db`. Collections with the same name but different beatmaps are
+/// resolved according to `policy`, and collections only in `other` are appended. The newer of the
+/// two versions is kept.
+pub fn merge_collection_dbs<'a>(
+    mut collection_db: CollectionDb<'a>,
+    other: CollectionDb<'a>,
+    policy: ConflictPolicy,
+    source: &str,
+    summary: &mut MergeSummary,
+) -> Result<CollectionDb<'a>, String> {
+    if policy == ConflictPolicy::PreferNewer {
+        return Err(
+            "collection.db doesn't store when collections were changed, so prefer-newer can't be \
+             used to merge collections."
+                .to_string(),
+        );
+    }
+    collection_db.version = collection_db.version.max(other.version);
+    for other_collection in other.collections {
+        let label = collection_label(&other_collection);
+        let existing = collection_db
+            .collections
+            .iter()
+            .position(|collection| collection.collection_name == other_collection.collection_name);
+        let idx = match existing {
+            Some(idx) => idx,
+            None => {
+                summary.collections_added += 1;
+                summary.record_hashes_added(
+                    source,
+                    label,
+                    format!(
+                        "added from right ({} beatmaps)",
+                        other_collection.md5_beatmap_hashes.len()
+                    ),
+                    &other_collection.md5_beatmap_hashes,
+                );
+                collection_db.collections.push(other_collection);
+                continue;
+            }
+        };
+        let left_hashes = collection_db.collections[idx]
+            .md5_beatmap_hashes
+            .iter()
+            .collect::<HashSet<_>>();
+        let right_hashes = other_collection
+            .md5_beatmap_hashes
+            .iter()
+            .collect::<HashSet<_>>();
+        if left_hashes == right_hashes {
+            continue;
+        }
+        match policy {
+            ConflictPolicy::KeepBoth => {
+                let collection = &mut collection_db.collections[idx];
+                let before = collection.md5_beatmap_hashes.len();
+                merge_collections(collection, other_collection);
+                let added = &collection.md5_beatmap_hashes[before..];
+                if !added.is_empty() {
+                    summary.collections_extended += 1;
+                }
+                summary.hashes_added += added.len();
+                summary
+                    .record_conflict(
+                        source,
+                        label,
+                        format!("combined, {} beatmaps added from right", added.len()),
+                    )
+                    .hashes_added = added.iter().map(|hash| hash.to_string()).collect();
+            }
+            ConflictPolicy::PreferLeft => {
+                summary.record_conflict(source, label, "kept left");
+            }
+            ConflictPolicy::PreferRight => {
+                collection_db.collections[idx] = other_collection;
+                summary.record_conflict(source, label, "kept right");
+            }
+            ConflictPolicy::Rename => {
+                let base_name = other_collection.collection_name.as_deref().unwrap_or("");
+                let new_name = unique_collection_name(&collection_db.collections, base_name);
+                let hashes = &other_collection.md5_beatmap_hashes;
+                summary.collections_added += 1;
+                summary.hashes_added += hashes.len();
+                summary
+                    .record_conflict(source, label, format!("added right as \"{}\"", new_name))
+                    .hashes_added = hashes.iter().map(|hash| hash.to_string()).collect();
+                let mut renamed = other_collection;
+                renamed.collection_name = Some(Cow::Owned(new_name));
+                collection_db.collections.push(renamed);
+            }
+            ConflictPolicy::PreferNewer => unreachable!(),
+        }
+    }
+    collection_db.number_of_collections = collection_db.collections.len() as i32;
+    Ok(collection_db)
+}
+
+fn merge_collections<'a>(collection: &mut Collection<'a>, other: Collection<'a>) {
+    let mut seen = HashSet::new();
+    let hashes = collection
+        .md5_beatmap_hashes
+        .drain(..)
+        .chain(other.md5_beatmap_hashes)
+        .filter(|&hash| seen.insert(hash))
+        .collect::<Vec<_>>();
+    collection.md5_beatmap_hashes = hashes;
+    collection.number_of_beatmaps = collection.md5_beatmap_hashes.len() as i32;
+}
+
+fn collection_label(collection: &Collection) -> String {
+    format!(
+        "collection \"{}\"",
+        collection.collection_name.as_deref().unwrap_or("")
+    )
+}
+
+/// Finds the first of "`name` (2)", "`name` (3)", ... that isn't used by any collection.
+fn unique_collection_name(collections: &[Collection], name: &str) -> String {
+    (2..)
+        .map(|n| format!("{} ({})", name, n))
+        .find(|candidate| {
+            collections
+                .iter()
+                .all(|collection| collection.collection_name.as_deref() != Some(candidate))
+        })
+        .unwrap()
+}
+
+/// Merges `other` into `collection_db`, with `base` being the database they both started out as.
+/// Unlike `merge_collection_dbs`, beatmaps and collections removed on either side stay removed.
+/// Collections are matched by name, or failing that, by having mostly the same beatmaps, so renames
+/// are followed. A collection that was renamed or deleted on one side but edited on the other is
+/// reported, and kept as edited unless `policy` prefers the side that renamed or deleted it.
+/// Collections new on both sides with the same name are resolved like in `merge_collection_dbs`.
+pub fn three_way_merge_collection_dbs<'a>(
+    collection_db: CollectionDb<'a>,
+    base: &CollectionDb,
+    other: CollectionDb<'a>,
+    policy: ConflictPolicy,
+    source: &str,
+    summary: &mut MergeSummary,
+) -> Result<CollectionDb<'a>, String> {
+    if policy == ConflictPolicy::PreferNewer {
+        return Err(
+            "collection.db doesn't store when collections were changed, so prefer-newer can't be \
+             used to merge collections."
+                .to_string(),
+        );
+    }
+    let left_matches = match_base_collections(&base.collections, &collection_db.collections);
+    let right_matches = match_base_collections(&base.collections, &other.collections);
+    let version = collection_db.version.max(other.version);
+    let mut left = collection_db
+        .collections
+        .into_iter()
+        .map(Some)
+        .collect::<Vec<_>>();
+    let mut right = other.collections.into_iter().map(Some).collect::<Vec<_>>();
+    let mut collections = Vec::new();
+    for (base_collection, (left_idx, right_idx)) in base
+        .collections
+        .iter()
+        .zip(left_matches.into_iter().zip(right_matches))
+    {
+        let left_collection = left_idx.and_then(|idx| left[idx].take());
+        let right_collection = right_idx.and_then(|idx| right[idx].take());
+        let label = collection_label(base_collection);
+        match (left_collection, right_collection) {
+            (Some(mut left_collection), Some(right_collection)) => {
+                let left_renamed =
+                    left_collection.collection_name != base_collection.collection_name;
+                let right_renamed =
+                    right_collection.collection_name != base_collection.collection_name;
+                let left_edited = !same_hashes(&left_collection, base_collection);
+                let right_edited = !same_hashes(&right_collection, base_collection);
+                if right_renamed {
+                    if left_renamed
+                        && left_collection.collection_name != right_collection.collection_name
+                    {
+                        let outcome = format!(
+                            "renamed to \"{}\" on left and \"{}\" on right",
+                            display_name(&left_collection),
+                            display_name(&right_collection)
+                        );
+                        summary.record_conflict(source, label.clone(), outcome);
+                        if policy == ConflictPolicy::PreferRight {
+                            left_collection.collection_name =
+                                right_collection.collection_name.clone();
+                        }
+                    } else if !left_renamed {
+                        let mut outcome = format!(
+                            "renamed to \"{}\" on right",
+                            display_name(&right_collection)
+                        );
+                        if left_edited {
+                            outcome.push_str(" but edited on left");
+                            summary.record_conflict(source, label.clone(), outcome);
+                        } else {
+                            summary.record(source, label.clone(), outcome);
+                        }
+                        left_collection.collection_name = right_collection.collection_name.clone();
+                    }
+                } else if left_renamed && right_edited {
+                    let outcome = format!(
+                        "renamed to \"{}\" on left but edited on right",
+                        display_name(&left_collection)
+                    );
+                    summary.record_conflict(source, label.clone(), outcome);
+                }
+                let before = left_collection.md5_beatmap_hashes.len();
+                let hashes = three_way_merge_hashes(
+                    base_collection,
+                    &left_collection.md5_beatmap_hashes,
+                    &right_collection.md5_beatmap_hashes,
+                );
+                let kept = hashes.len();
+                let added = hashes
+                    .iter()
+                    .copied()
+                    .filter(|hash| !left_collection.md5_beatmap_hashes.contains(hash))
+                    .collect::<Vec<_>>();
+                let removed = before + added.len() - kept;
+                if !added.is_empty() {
+                    summary.collections_extended += 1;
+                }
+                if !added.is_empty() || removed > 0 {
+                    let outcome = format!(
+                        "{} beatmaps added and {} removed from right",
+                        added.len(),
+                        removed
+                    );
+                    summary.record_hashes_added(source, label, outcome, &added);
+                }
+                left_collection.md5_beatmap_hashes = hashes;
+                left_collection.number_of_beatmaps = kept as i32;
+                collections.push(left_collection);
+            }
+            (Some(left_collection), None) => {
+                if !same_hashes(&left_collection, base_collection)
+                    || left_collection.collection_name != base_collection.collection_name
+                {
+                    if policy == ConflictPolicy::PreferRight {
+                        summary.record_conflict(
+                            source,
+                            label,
+                            "deleted on right but edited on left, deleted",
+                        );
+                    } else {
+                        summary.record_conflict(
+                            source,
+                            label,
+                            "deleted on right but edited on left, kept",
+                        );
+                        collections.push(left_collection);
+                    }
+                } else {
+                    summary.record(source, label, "deleted on right");
+                }
+            }
+            (None, Some(right_collection)) => {
+                if !same_hashes(&right_collection, base_collection)
+                    || right_collection.collection_name != base_collection.collection_name
+                {
+                    if policy == ConflictPolicy::PreferLeft {
+                        summary.record_conflict(
+                            source,
+                            label,
+                            "deleted on left but edited on right, deleted",
+                        );
+                    } else {
+                        summary.record_conflict(
+                            source,
+                            label,
+                            "deleted on left but edited on right, kept",
+                        );
+                        collections.push(right_collection);
+                    }
+                } else {
+                    summary.record(source, label, "deleted on left");
+                }
+            }
+            (None, None) => {}
+        }
+    }
+    // Whatever's left over didn't exist in the base database.
+    collections.extend(left.into_iter().flatten());
+    let merged = CollectionDb {
+        version,
+        number_of_collections: collections.len() as i32,
+        collections,
+    };
+    let new_on_right = right.into_iter().flatten().collect::<Vec<_>>();
+    let new_on_right = CollectionDb {
+        version,
+        number_of_collections: new_on_right.len() as i32,
+        collections: new_on_right,
+    };
+    merge_collection_dbs(merged, new_on_right, policy, source, summary)
+}
+
+/// For each collection in `base`, finds the index of the collection in `collections` that it became.
+/// Collections are first matched by name. Base collections with no collection of the same name are
+/// then matched to the unmatched collection sharing the most beatmaps with them, so long as at
+/// least half of the beatmaps in either are shared.
+fn match_base_collections(base: &[Collection], collections: &[Collection]) -> Vec<Option<usize>> {
+    let mut taken = vec![false; collections.len()];
+    let mut matches = base
+        .iter()
+        .map(|base_collection| {
+            let idx = collections.iter().position(|collection| {
+                collection.collection_name == base_collection.collection_name
+            })?;
+            taken[idx] = true;
+            Some(idx)
+        })
+        .collect::<Vec<_>>();
+    for (base_collection, base_match) in base.iter().zip(matches.iter_mut()) {
+        if base_match.is_some() {
+            continue;
+        }
+        let best = collections
+            .iter()
+            .enumerate()
+            .filter(|&(idx, collection)| {
+                !taken[idx]
+                    && base.iter().all(|other_base_collection| {
+                        other_base_collection.collection_name != collection.collection_name
+                    })
+            })
+            .map(|(idx, collection)| (idx, similarity(base_collection, collection)))
+            .filter(|&(_, similarity)| similarity >= 0.5)
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+        if let Some((idx, _)) = best {
+            taken[idx] = true;
+            *base_match = Some(idx);
+        }
+    }
+    matches
+}
+
+/// Jaccard similarity of the beatmaps in two collections. Two empty collections are identical.
+fn similarity(collection: &Collection, other: &Collection) -> f64 {
+    let hashes = collection.md5_beatmap_hashes.iter().collect::<HashSet<_>>();
+    let other_hashes = other.md5_beatmap_hashes.iter().collect::<HashSet<_>>();
+    let union = hashes.union(&other_hashes).count();
+    if union == 0 {
+        1.0
+    } else {
+        hashes.intersection(&other_hashes).count() as f64 / union as f64
+    }
+}
+
+fn same_hashes(collection: &Collection, other: &Collection) -> bool {
+    collection.md5_beatmap_hashes.iter().collect::<HashSet<_>>()
+        == other.md5_beatmap_hashes.iter().collect::<HashSet<_>>()
+}
+
+/// A beatmap is kept if it's on both sides, or if it's on one side and wasn't in the base (i.e. it
+/// was added). Beatmaps on one side that were in the base were removed by the other side. Left
+/// beatmaps come first, in their original order, followed by those added on the right.
+fn three_way_merge_hashes<'a>(
+    base: &Collection,
+    left: &[&'a str],
+    right: &[&'a str],
+) -> Vec<&'a str> {
+    let base = base
+        .md5_beatmap_hashes
+        .iter()
+        .copied()
+        .collect::<HashSet<_>>();
+    let left_set = left.iter().copied().collect::<HashSet<_>>();
+    let right_set = right.iter().copied().collect::<HashSet<_>>();
+    let mut seen = HashSet::new();
+    left.iter()
+        .filter(|&hash| right_set.contains(hash) || !base.contains(hash))
+        .chain(
+            right
+                .iter()
+                .filter(|&hash| !left_set.contains(hash) && !base.contains(hash)),
+        )
+        .copied()
+        .filter(|&hash| seen.insert(hash))
+        .collect()
+}
+
+fn display_name<'b>(collection: &'b Collection) -> &'b str {
+    collection.collection_name.as_deref().unwrap_or("")
+}
+
+/// Merges `other` into `scores_db`. Beatmaps are matched by their MD5 hash and their scores are
+/// combined. Scores present in both databases with different data are resolved according to
+/// `policy`. Beatmaps only in `other` are appended. The newer of the two versions is kept.
+pub fn merge_scores_dbs<'a>(
+    mut scores_db: ScoresDb<'a>,
+    other: ScoresDb<'a>,
+    policy: ConflictPolicy,
+    source: &str,
+    summary: &mut MergeSummary,
+) -> Result<ScoresDb<'a>, String> {
+    if policy == ConflictPolicy::Rename {
+        return Err("Scores have no name, so rename can't be used to merge scores.".to_string());
+    }
+    scores_db.version = scores_db.version.max(other.version);
+    let mut positions = HashMap::new();
+    for (idx, beatmap) in scores_db.beatmaps.iter().enumerate() {
+        positions.entry(beatmap.md5_beatmap_hash).or_insert(idx);
+    }
+    for other_beatmap in other.beatmaps {
+        if let Some(&idx) = positions.get(other_beatmap.md5_beatmap_hash) {
+            let beatmap = &mut scores_db.beatmaps[idx];
+            merge_scores_db_beatmaps(beatmap, other_beatmap, policy, source, summary);
+        } else {
+            let number_of_scores = other_beatmap.scores.as_ref().map_or(0, Vec::len);
+            summary.scores_added += number_of_scores;
+            summary.record(
+                source,
+                format!("beatmap {}", other_beatmap.md5_beatmap_hash),
+                format!("added from right ({} scores)", number_of_scores),
+            );
+            positions.insert(other_beatmap.md5_beatmap_hash, scores_db.beatmaps.len());
+            scores_db.beatmaps.push(other_beatmap);
+        }
+    }
+    scores_db.number_of_beatmaps = scores_db.beatmaps.len() as i32;
+    Ok(scores_db)
+}
+
+fn merge_scores_db_beatmaps<'a>(
+    beatmap: &mut ScoresDbBeatmap<'a>,
+    other: ScoresDbBeatmap<'a>,
+    policy: ConflictPolicy,
+    source: &str,
+    summary: &mut MergeSummary,
+) {
+    let mut scores = beatmap.scores.take().unwrap_or_default();
+    let mut keys = ScoreKeys::new(&scores);
+    let mut added = 0;
+    let mut duplicates = 0;
+    for other_score in other.scores.unwrap_or_default() {
+        let idx = match keys.position(&other_score) {
+            Some(idx) => idx,
+            None => {
+                keys.insert(scores.len(), &other_score);
+                scores.push(other_score);
+                added += 1;
+                continue;
+            }
+        };
+        if scores[idx] == other_score {
+            duplicates += 1;
+            continue;
+        }
+        let label = score_label(&other_score);
+        match policy {
+            ConflictPolicy::KeepBoth => {
+                summary.record_conflict(source, label, "kept left (can't keep both scores)");
+            }
+            ConflictPolicy::PreferLeft => {
+                summary.record_conflict(source, label, "kept left");
+            }
+            ConflictPolicy::PreferRight => {
+                keys.remove(idx, &scores[idx]);
+                keys.insert(idx, &other_score);
+                scores[idx] = other_score;
+                summary.record_conflict(source, label, "kept right");
+            }
+            ConflictPolicy::PreferNewer => {
+                if other_score.replay_timestamp > scores[idx].replay_timestamp {
+                    keys.remove(idx, &scores[idx]);
+                    keys.insert(idx, &other_score);
+                    scores[idx] = other_score;
+                    summary.record_conflict(source, label, "kept right (newer)");
+                } else {
+                    summary.record_conflict(source, label, "kept left (newer)");
+                }
+            }
+            ConflictPolicy::Rename => unreachable!(),
+        }
+    }
+    if added > 0 {
+        summary.scores_added += added;
+        summary.record(
+            source,
+            format!("beatmap {}", beatmap.md5_beatmap_hash),
+            format!("added {} scores from right", added),
+        );
+    }
+    if duplicates > 0 {
+        summary.duplicate_scores_skipped += duplicates;
+        summary.record(
+            source,
+            format!("beatmap {}", beatmap.md5_beatmap_hash),
+            format!("skipped {} duplicate scores from right", duplicates),
+        );
+    }
+    // The game lists local scores from highest to lowest.
+    scores.sort_by_key(|score| Reverse(score.replay_score));
+    beatmap.number_of_scores = scores.len() as i32;
+    beatmap.scores = if scores.is_empty() {
+        None
+    } else {
+        Some(scores)
+    };
+}
+
+/// Scores are identified by their replay hash. Should that be missing, the online score ID is used
+/// instead, which is 0 for scores that were never submitted.
+pub(crate) fn is_same_score(score: &Score, other: &Score) -> bool {
+    if !score.md5_replay_hash.is_empty() && !other.md5_replay_hash.is_empty() {
+        score.md5_replay_hash == other.md5_replay_hash
+    } else {
+        score.online_score_id != 0 && score.online_score_id == other.online_score_id
+    }
+}
+
+/// The keys `is_same_score` identifies scores by, mapped to the positions of the scores that have
+/// them, so a score can be looked up among many others without comparing it against each of them.
+pub(crate) struct ScoreKeys<'a> {
+    replay_hashes: HashMap<&'a str, Vec<usize>>,
+    // Online IDs of the scores without a replay hash, which are only matched by online ID.
+    hashless_online_ids: HashMap<i64, Vec<usize>>,
+    online_ids: HashMap<i64, Vec<usize>>,
+}
+
+impl<'a> ScoreKeys<'a> {
+    pub(crate) fn new<'b>(scores: impl IntoIterator<Item = &'b Score<'a>>) -> Self
+    where
+        'a: 'b,
+    {
+        let mut keys = ScoreKeys {
+            replay_hashes: HashMap::new(),
+            hashless_online_ids: HashMap::new(),
+            online_ids: HashMap::new(),
+        };
+        for (idx, score) in scores.into_iter().enumerate() {
+            keys.insert(idx, score);
+        }
+        keys
+    }
+
+    /// Record that the score at `idx` is `score`. Positions are kept in ascending order.
+    pub(crate) fn insert(&mut self, idx: usize, score: &Score<'a>) {
+        let insert = |positions: &mut Vec<usize>| {
+            if let Err(at) = positions.binary_search(&idx) {
+                positions.insert(at, idx);
+            }
+        };
+        if score.md5_replay_hash.is_empty() {
+            insert(
+                self.hashless_online_ids
+                    .entry(score.online_score_id)
+                    .or_default(),
+            );
+        } else {
+            insert(self.replay_hashes.entry(score.md5_replay_hash).or_default());
+        }
+        insert(self.online_ids.entry(score.online_score_id).or_default());
+    }
+
+    /// Forget that the score at `idx` is `score`, e.g. before replacing it.
+    pub(crate) fn remove(&mut self, idx: usize, score: &Score<'a>) {
+        let remove = |positions: Option<&mut Vec<usize>>| {
+            if let Some(positions) = positions {
+                positions.retain(|&position| position != idx);
+            }
+        };
+        if score.md5_replay_hash.is_empty() {
+            remove(self.hashless_online_ids.get_mut(&score.online_score_id));
+        } else {
+            remove(self.replay_hashes.get_mut(score.md5_replay_hash));
+        }
+        remove(self.online_ids.get_mut(&score.online_score_id));
+    }
+
+    /// The position of the first score `is_same_score` holds between it and `score`, if any.
+    pub(crate) fn position(&self, score: &Score) -> Option<usize> {
+        let first = |positions: Option<&Vec<usize>>| positions.and_then(|p| p.first().copied());
+        let by_online_id = |ids: &HashMap<i64, Vec<usize>>| {
+            if score.online_score_id == 0 {
+                None
+            } else {
+                first(ids.get(&score.online_score_id))
+            }
+        };
+        if score.md5_replay_hash.is_empty() {
+            by_online_id(&self.online_ids)
+        } else {
+            let by_hash = first(self.replay_hashes.get(score.md5_replay_hash));
+            match (by_hash, by_online_id(&self.hashless_online_ids)) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            }
+        }
+    }
+
+    /// Whether `is_same_score` holds between `score` and any of the scores.
+    pub(crate) fn contains(&self, score: &Score) -> bool {
+        self.position(score).is_some()
+    }
+}
+
+pub(crate) fn score_label(score: &Score) -> String {
+    if score.md5_replay_hash.is_empty() {
+        format!(
+            "score #{} on beatmap {}",
+            score.online_score_id, score.md5_beatmap_hash
+        )
+    } else {
+        format!(
+            "score {} on beatmap {}",
+            score.md5_replay_hash, score.md5_beatmap_hash
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::databases::osu::primitives::{GameplayMode, Mods};
+    use crate::deserialize_primitives::datetime_from_ticks;
+
+    fn score(md5_replay_hash: &str, online_score_id: i64) -> Score<'_> {
+        Score {
+            gameplay_mode: GameplayMode::Standard,
+            score_version: 20211231,
+            md5_beatmap_hash: "",
+            player_name: None,
+            md5_replay_hash,
+            number_of_300s: 0,
+            number_of_100s: 0,
+            number_of_50s: 0,
+            number_of_gekis: 0,
+            number_of_katus: 0,
+            number_of_misses: 0,
+            replay_score: 0,
+            max_combo: 0,
+            perfect_combo: false,
+            mods_used: Mods::NONE,
+            empty_string: None,
+            replay_timestamp: datetime_from_ticks(0),
+            negative_one: -1,
+            online_score_id,
+        }
+    }
+
+    #[test]
+    fn score_keys_find_the_same_score_as_is_same_score() {
+        let hashes = ["", "a", "b"];
+        let mut scores = Vec::new();
+        for &hash in &hashes {
+            for id in 0..3 {
+                scores.push(score(hash, id));
+            }
+        }
+        // Every ordered selection of up to three scores, looked up against every score.
+        for a in 0..scores.len() {
+            for b in 0..scores.len() {
+                for c in 0..scores.len() {
+                    let existing = vec![scores[a].clone(), scores[b].clone(), scores[c].clone()];
+                    let keys = ScoreKeys::new(&existing);
+                    for query in &scores {
+                        let expected = existing.iter().position(|s| is_same_score(s, query));
+                        assert_eq!(keys.position(query), expected, "{:?}", query);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn score_keys_follow_replaced_scores() {
+        let mut keys = ScoreKeys::new(&[score("", 5), score("b", 6)]);
+        keys.remove(0, &score("", 5));
+        keys.insert(0, &score("a", 5));
+        assert_eq!(keys.position(&score("a", 0)), Some(0));
+        assert_eq!(keys.position(&score("c", 5)), None);
+        assert_eq!(keys.position(&score("", 5)), Some(0));
+        assert_eq!(keys.position(&score("b", 0)), Some(1));
+    }
 }
@@ -7,7 +7,17 @@ macro_rules! continue_if {
 }
 
 pub mod collection;
+pub mod collection_command;
+pub mod convert;
+pub mod dangling;
 pub mod database;
+pub mod diff;
+pub mod id_list;
 pub mod merge;
+pub mod osdb;
 pub mod osu;
+pub mod playlist;
+pub mod random_picker;
+pub mod remap;
 pub mod scores;
+pub mod smart_collection;
@@ -0,0 +1,487 @@
+use crate::databases::{
+    collection::{collection::Collection, collectiondb::CollectionDb},
+    collection_command::{find_collection, is_md5_hash, read_osu_db},
+    merge::{merge_collection_dbs, ConflictPolicy, MergeSummary},
+    osu::{
+        beatmap::Beatmap,
+        osudb::OsuDb,
+        primitives::{GameplayMode, Mods},
+    },
+};
+use crate::deserialize_primitives::*;
+use crate::output::{write_atomically, Output};
+use crate::read_error::{DbFileParseError, ParseErrorKind::OsdbError, ParseFileResult};
+use crate::serialize_primitives::*;
+use chrono::{Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str;
+use structopt::StructOpt;
+
+/// Collections in the format used by Collection Manager, which is how collections are usually
+/// shared. Unlike collection.db, beatmaps carry enough metadata to be found again online. Version 7
+/// and later gzip everything after the leading version string.
+#[derive(Clone, Debug)]
+pub struct Osdb {
+    pub version: u8,
+    /// Minimal files leave out each beatmap's artist, title, and difficulty.
+    pub minimal: bool,
+    pub date: NaiveDateTime,
+    pub editor: String,
+    pub collections: Vec<OsdbCollection>,
+}
+
+#[derive(Clone, Debug)]
+pub struct OsdbCollection {
+    pub name: String,
+    pub online_id: i32,
+    pub beatmaps: Vec<OsdbBeatmap>,
+    /// Beatmaps that nothing but the hash is known about.
+    pub hash_only_beatmaps: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct OsdbBeatmap {
+    pub beatmap_id: i32,
+    pub beatmap_set_id: i32,
+    pub artist_name: String,
+    pub song_title: String,
+    pub difficulty: String,
+    pub md5_beatmap_hash: String,
+    pub user_comment: String,
+    pub gameplay_mode: u8,
+    pub star_rating: f64,
+}
+
+/// The latest version, which is what gets written unless asked for a plain file.
+const GZIP_VERSION: u8 = 8;
+/// The latest version from before the body was gzipped.
+const PLAIN_VERSION: u8 = 6;
+const FOOTER: &str = "By Piotrekol";
+
+impl Osdb {
+    pub fn read_from_bytes(bytes: &[u8]) -> ParseFileResult<Self> {
+        let mut index = 0;
+        let i = &mut index;
+        let version_string = read_dotnet_string(bytes, i)?;
+        let (version, minimal) = parse_version(&version_string).ok_or_else(|| {
+            let err_msg = format!("Unknown .osdb version: {}", version_string);
+            DbFileParseError::new(OsdbError, err_msg.as_str())
+        })?;
+        if version >= 7 {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(&bytes[*i..])
+                .read_to_end(&mut decompressed)
+                .map_err(|e| {
+                    let err_msg = format!("Failed to decompress .osdb file ({})", e);
+                    DbFileParseError::new(OsdbError, err_msg.as_str())
+                })?;
+            let mut index = 0;
+            let i = &mut index;
+            // The version string is repeated at the start of the compressed part.
+            read_dotnet_string(&decompressed, i)?;
+            Self::read_body(version, minimal, &decompressed, i)
+        } else {
+            Self::read_body(version, minimal, bytes, i)
+        }
+    }
+
+    fn read_body(version: u8, minimal: bool, bytes: &[u8], i: &mut usize) -> ParseFileResult<Self> {
+        let date = datetime_from_oa_date(read_double(bytes, i)?);
+        let editor = read_dotnet_string(bytes, i)?;
+        let number_of_collections = read_int(bytes, i)?;
+        let mut collections = Vec::with_capacity(number_of_collections.max(0) as usize);
+        for _ in 0..number_of_collections {
+            let name = read_dotnet_string(bytes, i)?;
+            let online_id = if version >= 7 {
+                read_int(bytes, i)?
+            } else {
+                -1
+            };
+            let number_of_beatmaps = read_int(bytes, i)?;
+            let mut beatmaps = Vec::with_capacity(number_of_beatmaps.max(0) as usize);
+            for _ in 0..number_of_beatmaps {
+                let beatmap_id = read_int(bytes, i)?;
+                let beatmap_set_id = if version >= 2 {
+                    read_int(bytes, i)?
+                } else {
+                    -1
+                };
+                let (artist_name, song_title, difficulty) = if minimal {
+                    (String::new(), String::new(), String::new())
+                } else {
+                    (
+                        read_dotnet_string(bytes, i)?,
+                        read_dotnet_string(bytes, i)?,
+                        read_dotnet_string(bytes, i)?,
+                    )
+                };
+                let md5_beatmap_hash = read_dotnet_string(bytes, i)?;
+                let user_comment = if version >= 4 {
+                    read_dotnet_string(bytes, i)?
+                } else {
+                    String::new()
+                };
+                let gameplay_mode = if version >= 5 {
+                    read_byte(bytes, i)?
+                } else {
+                    0
+                };
+                let star_rating = if version >= 6 {
+                    read_double(bytes, i)?
+                } else {
+                    0.0
+                };
+                beatmaps.push(OsdbBeatmap {
+                    beatmap_id,
+                    beatmap_set_id,
+                    artist_name,
+                    song_title,
+                    difficulty,
+                    md5_beatmap_hash,
+                    user_comment,
+                    gameplay_mode,
+                    star_rating,
+                });
+            }
+            let mut hash_only_beatmaps = Vec::new();
+            if version >= 3 {
+                let number_of_hashes = read_int(bytes, i)?;
+                for _ in 0..number_of_hashes {
+                    hash_only_beatmaps.push(read_dotnet_string(bytes, i)?);
+                }
+            }
+            collections.push(OsdbCollection {
+                name,
+                online_id,
+                beatmaps,
+                hash_only_beatmaps,
+            });
+        }
+        Ok(Osdb {
+            version,
+            minimal,
+            date,
+            editor,
+            collections,
+        })
+    }
+
+    /// Write the file in the layout used by its version, compressing it if that version does.
+    pub fn write_to_bytes(&self) -> Vec<u8> {
+        let mut version_string = match self.version {
+            1 => "o!dm".to_string(),
+            version => format!("o!dm{}", version),
+        };
+        if self.minimal {
+            version_string.push_str("min");
+        }
+        let mut bytes = Vec::new();
+        write_dotnet_string(&version_string, &mut bytes);
+        if self.version >= 7 {
+            let mut body = Vec::new();
+            write_dotnet_string(&version_string, &mut body);
+            self.write_body(&mut body);
+            let mut encoder = GzEncoder::new(bytes, Compression::default());
+            // Writing to a `Vec` can't fail.
+            encoder.write_all(&body).unwrap();
+            encoder.finish().unwrap()
+        } else {
+            self.write_body(&mut bytes);
+            bytes
+        }
+    }
+
+    fn write_body(&self, bytes: &mut Vec<u8>) {
+        write_double(oa_date_from_datetime(self.date), bytes);
+        write_dotnet_string(&self.editor, bytes);
+        write_int(self.collections.len() as i32, bytes);
+        for collection in &self.collections {
+            write_dotnet_string(&collection.name, bytes);
+            if self.version >= 7 {
+                write_int(collection.online_id, bytes);
+            }
+            write_int(collection.beatmaps.len() as i32, bytes);
+            for beatmap in &collection.beatmaps {
+                write_int(beatmap.beatmap_id, bytes);
+                if self.version >= 2 {
+                    write_int(beatmap.beatmap_set_id, bytes);
+                }
+                if !self.minimal {
+                    write_dotnet_string(&beatmap.artist_name, bytes);
+                    write_dotnet_string(&beatmap.song_title, bytes);
+                    write_dotnet_string(&beatmap.difficulty, bytes);
+                }
+                write_dotnet_string(&beatmap.md5_beatmap_hash, bytes);
+                if self.version >= 4 {
+                    write_dotnet_string(&beatmap.user_comment, bytes);
+                }
+                if self.version >= 5 {
+                    write_byte(beatmap.gameplay_mode, bytes);
+                }
+                if self.version >= 6 {
+                    write_double(beatmap.star_rating, bytes);
+                }
+            }
+            if self.version >= 3 {
+                write_int(collection.hash_only_beatmaps.len() as i32, bytes);
+                for hash in &collection.hash_only_beatmaps {
+                    write_dotnet_string(hash, bytes);
+                }
+            }
+        }
+        write_dotnet_string(FOOTER, bytes);
+    }
+}
+
+impl OsdbBeatmap {
+    fn from_beatmap(beatmap: &Beatmap) -> Self {
+        let star_ratings = match beatmap.gameplay_mode {
+            GameplayMode::Standard => &beatmap.mod_combo_star_ratings_standard,
+            GameplayMode::Taiko => &beatmap.mod_combo_star_ratings_taiko,
+            GameplayMode::Ctb => &beatmap.mod_combo_star_ratings_ctb,
+            GameplayMode::Mania => &beatmap.mod_combo_star_ratings_mania,
+        };
+        let star_rating = star_ratings
+            .iter()
+            .flatten()
+            .find(|&&(mods, _)| mods == Mods::NONE)
+            .map_or(0.0, |&(_, star_rating)| star_rating);
+        OsdbBeatmap {
+            beatmap_id: beatmap.beatmap_id,
+            beatmap_set_id: beatmap.beatmap_set_id,
+            artist_name: beatmap.artist_name.unwrap_or("").to_string(),
+            song_title: beatmap.song_title.unwrap_or("").to_string(),
+            difficulty: beatmap.difficulty.unwrap_or("").to_string(),
+            md5_beatmap_hash: beatmap.md5_beatmap_hash.to_string(),
+            user_comment: String::new(),
+            gameplay_mode: beatmap.gameplay_mode as u8,
+            star_rating,
+        }
+    }
+}
+
+/// Version strings are `o!dm` for the first version and `o!dm2` through `o!dm8` after that, with a
+/// `min` suffix for minimal files.
+fn parse_version(version_string: &str) -> Option<(u8, bool)> {
+    let (version_string, minimal) = match version_string.strip_suffix("min") {
+        Some(version_string) => (version_string, true),
+        None => (version_string, false),
+    };
+    let version = match version_string.strip_prefix("o!dm")? {
+        "" => 1,
+        number => number.parse::<u8>().ok().filter(|v| (2..=8).contains(v))?,
+    };
+    Some((version, minimal))
+}
+
+/// Strings as written by .NET's `BinaryWriter`: a ULEB128 length followed by UTF-8, without the
+/// indicator byte that osu! puts in front of its strings.
+fn read_dotnet_string(bytes: &[u8], i: &mut usize) -> ParseFileResult<String> {
+    let length = read_uleb128(bytes, i)?;
+    if *i + length > bytes.len() {
+        return Err(DbFileParseError::new(
+            OsdbError,
+            "String length goes past end of file.",
+        ));
+    }
+    let string = str::from_utf8(&bytes[*i..*i + length]).map_err(|e| {
+        let err_msg = format!("Error reading string ({})", e);
+        DbFileParseError::new(OsdbError, err_msg.as_str())
+    })?;
+    *i += length;
+    Ok(string.to_string())
+}
+
+fn write_dotnet_string(string: &str, bytes: &mut Vec<u8>) {
+    write_uleb128(string.len(), bytes);
+    bytes.extend_from_slice(string.as_bytes());
+}
+
+/// OLE Automation dates count days, including a fractional part for the time, since 1899-12-30.
+fn oa_date_epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(1899, 12, 30)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .unwrap()
+}
+
+fn datetime_from_oa_date(oa_date: f64) -> NaiveDateTime {
+    oa_date_epoch() + ChronoDuration::milliseconds((oa_date * 86_400_000.0).round() as i64)
+}
+
+fn oa_date_from_datetime(datetime: NaiveDateTime) -> f64 {
+    (datetime - oa_date_epoch()).num_milliseconds() as f64 / 86_400_000.0
+}
+
+#[derive(StructOpt)]
+pub struct Export {
+    #[structopt(value_name = "NAME", required = true, min_values = 1)]
+    names: Vec<String>,
+    #[structopt(
+        name = "osu!.db path",
+        long = "osu-db",
+        value_name = "PATH",
+        help = "osu!.db to fill in beatmap IDs, artists, titles, and difficulties from. Beatmaps \
+                that aren't in it are exported by hash only."
+    )]
+    osu_db_path: Option<String>,
+    #[structopt(
+        long = "plain",
+        help = "Write an older, uncompressed version of the format rather than the gzipped one."
+    )]
+    plain: bool,
+    #[structopt(flatten)]
+    output: Output,
+}
+
+impl Export {
+    /// Export the named collections from the collection.db in `bytes` to an .osdb file.
+    pub fn run(&self, jobs: usize, bytes: &[u8]) {
+        if !self.output.can_write() {
+            return;
+        }
+        let collection_db =
+            CollectionDb::read_from_bytes(jobs, bytes).expect("Failed to parse database.");
+        let osu_buffer = self.osu_db_path.as_ref().map(|path| read_osu_db(path));
+        let osu_db = osu_buffer.as_ref().map(|osu_buffer| {
+            OsuDb::read_from_bytes(jobs, osu_buffer).expect("Failed to parse osu!.db.")
+        });
+        let beatmaps = osu_db
+            .as_ref()
+            .map_or_else(HashMap::new, |osu_db| osu_db.beatmaps_by_hash());
+        let mut collections = Vec::new();
+        for name in &self.names {
+            let collection = match find_collection(&collection_db, name) {
+                Some(index) => &collection_db.collections[index],
+                None => {
+                    println!("No collection named \"{}\".", name);
+                    return;
+                }
+            };
+            let mut osdb_collection = OsdbCollection {
+                name: name.clone(),
+                online_id: -1,
+                beatmaps: Vec::new(),
+                hash_only_beatmaps: Vec::new(),
+            };
+            for &hash in &collection.md5_beatmap_hashes {
+                match beatmaps.get(hash) {
+                    Some(beatmap) => osdb_collection
+                        .beatmaps
+                        .push(OsdbBeatmap::from_beatmap(beatmap)),
+                    None => osdb_collection.hash_only_beatmaps.push(hash.to_string()),
+                }
+            }
+            println!(
+                "Exporting collection \"{}\": {} beatmaps with metadata, {} by hash only.",
+                name,
+                osdb_collection.beatmaps.len(),
+                osdb_collection.hash_only_beatmaps.len()
+            );
+            collections.push(osdb_collection);
+        }
+        let osdb = Osdb {
+            version: if self.plain {
+                PLAIN_VERSION
+            } else {
+                GZIP_VERSION
+            },
+            minimal: false,
+            date: Local::now().naive_local(),
+            editor: "osu-db-manager".to_string(),
+            collections,
+        };
+        self.output
+            .write(&osdb.write_to_bytes())
+            .expect("Failed to write .osdb file.");
+        println!("Wrote {}", self.output.path);
+    }
+}
+
+#[derive(StructOpt)]
+pub struct Import {
+    #[structopt(value_name = "OSDB-PATH")]
+    osdb_path: String,
+    #[structopt(
+        name = "conflict policy",
+        long = "on-conflict",
+        value_name = "POLICY",
+        possible_values(&["keep-both", "prefer-left", "prefer-right", "rename"]),
+        default_value = "keep-both",
+        parse(try_from_str)
+    )]
+    on_conflict: ConflictPolicy,
+}
+
+impl Import {
+    /// Merge the collections in an .osdb file into the collection.db in `bytes`, and save it back
+    /// to `db_path`. Collections are merged the same way `merge` merges two collection.db files,
+    /// with the .osdb file on the right.
+    pub fn run(&self, jobs: usize, db_path: &str, bytes: &[u8], backups: usize) {
+        let mut osdb_buffer = Vec::new();
+        let mut file = File::open(&self.osdb_path).expect("Failed to open .osdb file.");
+        file.read_to_end(&mut osdb_buffer)
+            .expect("Failed to read .osdb file.");
+        let osdb = Osdb::read_from_bytes(&osdb_buffer).expect("Failed to parse .osdb file.");
+        let collection_db =
+            CollectionDb::read_from_bytes(jobs, bytes).expect("Failed to parse database.");
+        // osu! reads every hash in collection.db as 32 bytes, so anything else would corrupt it.
+        let mut invalid = 0;
+        let imported = CollectionDb {
+            version: collection_db.version,
+            number_of_collections: osdb.collections.len() as i32,
+            collections: osdb
+                .collections
+                .iter()
+                .map(|collection| {
+                    let md5_beatmap_hashes = collection
+                        .beatmaps
+                        .iter()
+                        .map(|beatmap| beatmap.md5_beatmap_hash.as_str())
+                        .chain(collection.hash_only_beatmaps.iter().map(String::as_str))
+                        .filter(|hash| {
+                            let valid = hash.is_empty() || is_md5_hash(hash);
+                            if !valid {
+                                invalid += 1;
+                            }
+                            valid
+                        })
+                        .collect::<Vec<_>>();
+                    Collection {
+                        collection_name: Some(Cow::Borrowed(collection.name.as_str())),
+                        number_of_beatmaps: md5_beatmap_hashes.len() as i32,
+                        md5_beatmap_hashes,
+                    }
+                })
+                .collect(),
+        };
+        if invalid > 0 {
+            println!(
+                "Skipped {} entries in {} that aren't MD5 beatmap hashes.",
+                invalid, self.osdb_path
+            );
+        }
+        let mut summary = MergeSummary::default();
+        let merged = match merge_collection_dbs(
+            collection_db,
+            imported,
+            self.on_conflict,
+            &self.osdb_path,
+            &mut summary,
+        ) {
+            Ok(merged) => merged,
+            Err(msg) => {
+                println!("{}", msg);
+                return;
+            }
+        };
+        write_atomically(Path::new(db_path), &merged.write_to_bytes(), backups)
+            .expect("Failed to write collection database.");
+        print!("{}", summary);
+        println!("Imported {} into {}", self.osdb_path, db_path);
+    }
+}
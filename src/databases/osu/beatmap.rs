@@ -1,8 +1,12 @@
-use chrono::NaiveDate;
+use chrono::NaiveDateTime;
 
-use crate::databases::osu::{primitives::*, versions::ReadVersionSpecificData};
+use crate::databases::osu::{
+    primitives::*,
+    versions::{ReadVersionSpecificData, WriteVersionSpecificData},
+};
 use crate::deserialize_primitives::*;
 use crate::read_error::{DbFileParseError, ParseErrorKind::*, ParseFileResult};
+use crate::serialize_primitives::*;
 
 /// Beatmap struct according to documentation linked in README.
 #[derive(Clone, Debug)]
@@ -21,20 +25,20 @@ pub struct Beatmap<'a> {
     pub number_of_hitcircles: i16,
     pub number_of_sliders: i16,
     pub number_of_spinners: i16,
-    pub last_modification_time: NaiveDate,
+    pub last_modification_time: NaiveDateTime,
     pub approach_rate: ByteSingle,
     pub circle_size: ByteSingle,
     pub hp_drain: ByteSingle,
     pub overall_difficulty: ByteSingle,
     pub slider_velocity: f64,
     pub num_mod_combo_star_ratings_standard: Option<i32>,
-    pub mod_combo_star_ratings_standard: Option<Vec<(i32, f64)>>,
+    pub mod_combo_star_ratings_standard: Option<Vec<(Mods, f64)>>,
     pub num_mod_combo_star_ratings_taiko: Option<i32>,
-    pub mod_combo_star_ratings_taiko: Option<Vec<(i32, f64)>>,
+    pub mod_combo_star_ratings_taiko: Option<Vec<(Mods, f64)>>,
     pub num_mod_combo_star_ratings_ctb: Option<i32>,
-    pub mod_combo_star_ratings_ctb: Option<Vec<(i32, f64)>>,
+    pub mod_combo_star_ratings_ctb: Option<Vec<(Mods, f64)>>,
     pub num_mod_combo_star_ratings_mania: Option<i32>,
-    pub mod_combo_star_ratings_mania: Option<Vec<(i32, f64)>>,
+    pub mod_combo_star_ratings_mania: Option<Vec<(Mods, f64)>>,
     pub drain_time: i32,
     pub total_time: i32,
     pub preview_offset_from_start_ms: i32,
@@ -55,10 +59,10 @@ pub struct Beatmap<'a> {
     pub online_offset: i16,
     pub font_used_for_song_title: Option<&'a str>,
     pub unplayed: bool,
-    pub last_played: NaiveDate,
+    pub last_played: NaiveDateTime,
     pub is_osz2: bool,
     pub beatmap_folder_name: Option<&'a str>,
-    pub last_checked_against_repo: NaiveDate,
+    pub last_checked_against_repo: NaiveDateTime,
     pub ignore_beatmap_sound: bool,
     pub ignore_beatmap_skin: bool,
     pub disable_storyboard: bool,
@@ -200,6 +204,113 @@ impl<'a> Beatmap<'a> {
         })
     }
 
+    /// Write a beatmap entry in the layout used by `T`. The entry size and the numbers of mod combo
+    /// star ratings and timing points are recomputed from the data rather than taken from their
+    /// fields, so the written entry is always consistent.
+    pub fn write_to_bytes<T: WriteVersionSpecificData>(&self, bytes: &mut Vec<u8>) {
+        let mut entry = Vec::new();
+        let e = &mut entry;
+        write_str_utf8(self.artist_name, e);
+        write_str_utf8(self.artist_name_unicode, e);
+        write_str_utf8(self.song_title, e);
+        write_str_utf8(self.song_title_unicode, e);
+        write_player_name(self.creator_name, e);
+        write_str_utf8(self.difficulty, e);
+        write_str_utf8(self.audio_file_name, e);
+        write_md5_hash(self.md5_beatmap_hash, e);
+        write_str_utf8(self.dotosu_file_name, e);
+        self.ranked_status.write_to_bytes(e);
+        write_short(self.number_of_hitcircles, e);
+        write_short(self.number_of_sliders, e);
+        write_short(self.number_of_spinners, e);
+        write_datetime(self.last_modification_time, e);
+        T::write_arcshpod(self.approach_rate, e);
+        T::write_arcshpod(self.circle_size, e);
+        T::write_arcshpod(self.hp_drain, e);
+        T::write_arcshpod(self.overall_difficulty, e);
+        write_double(self.slider_velocity, e);
+        T::write_mod_combo_star_ratings(self.mod_combo_star_ratings_standard.as_ref(), e);
+        T::write_mod_combo_star_ratings(self.mod_combo_star_ratings_taiko.as_ref(), e);
+        T::write_mod_combo_star_ratings(self.mod_combo_star_ratings_ctb.as_ref(), e);
+        T::write_mod_combo_star_ratings(self.mod_combo_star_ratings_mania.as_ref(), e);
+        write_int(self.drain_time, e);
+        write_int(self.total_time, e);
+        write_int(self.preview_offset_from_start_ms, e);
+        write_int(self.timing_points.len() as i32, e);
+        for timing_point in &self.timing_points {
+            timing_point.write_to_bytes(e);
+        }
+        write_int(self.beatmap_id, e);
+        write_int(self.beatmap_set_id, e);
+        write_int(self.thread_id, e);
+        write_byte(self.standard_grade, e);
+        write_byte(self.taiko_grade, e);
+        write_byte(self.ctb_grade, e);
+        write_byte(self.mania_grade, e);
+        write_short(self.local_offset, e);
+        write_single(self.stack_leniency, e);
+        self.gameplay_mode.write_to_bytes(e);
+        write_str_utf8(self.song_source, e);
+        write_str_utf8(self.song_tags, e);
+        write_short(self.online_offset, e);
+        write_str_utf8(self.font_used_for_song_title, e);
+        write_boolean(self.unplayed, e);
+        write_datetime(self.last_played, e);
+        write_boolean(self.is_osz2, e);
+        write_str_utf8(self.beatmap_folder_name, e);
+        write_datetime(self.last_checked_against_repo, e);
+        write_boolean(self.ignore_beatmap_sound, e);
+        write_boolean(self.ignore_beatmap_skin, e);
+        write_boolean(self.disable_storyboard, e);
+        write_boolean(self.disable_video, e);
+        write_boolean(self.visual_override, e);
+        T::write_unknown_short(self.unknown_short, e);
+        write_int(self.offset_from_song_start_in_editor_ms, e);
+        write_byte(self.mania_scroll_speed, e);
+        T::write_sized_entry(&entry, bytes);
+    }
+
+    /// The BPM that's in effect for the longest part of the beatmap, which is what osu! shows when
+    /// a beatmap's BPM changes.
+    pub fn main_bpm(&self) -> Option<f64> {
+        let bpms = self
+            .timing_points
+            .iter()
+            .filter_map(|timing_point| timing_point.bpm().map(|bpm| (timing_point.offset(), bpm)))
+            .collect::<Vec<_>>();
+        let mut durations: Vec<(f64, f64)> = Vec::new();
+        for (k, &(offset, bpm)) in bpms.iter().enumerate() {
+            let end = bpms
+                .get(k + 1)
+                .map_or(self.total_time as f64, |&(next_offset, _)| next_offset);
+            let duration = (end - offset).max(0.0);
+            match durations
+                .iter_mut()
+                .find(|(other_bpm, _)| (other_bpm - bpm).abs() < 0.01)
+            {
+                Some((_, total)) => *total += duration,
+                None => durations.push((bpm, duration)),
+            }
+        }
+        durations
+            .into_iter()
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .map(|(bpm, _)| bpm)
+    }
+
+    /// "Artist - Title [Difficulty] (creator)" followed by the beatmap and set IDs.
+    pub fn label(&self) -> String {
+        format!(
+            "{} - {} [{}] ({}) (beatmap ID {}, set ID {})",
+            self.artist_name.unwrap_or(""),
+            self.song_title.unwrap_or(""),
+            self.difficulty.unwrap_or(""),
+            self.creator_name.unwrap_or(""),
+            self.beatmap_id,
+            self.beatmap_set_id
+        )
+    }
+
     pub fn display(&self) {
         if self.entry_size.is_some() {
             println!("    entry size: {}", self.entry_size.as_ref().unwrap());
@@ -3,12 +3,15 @@ use crate::databases::osu::{
     primitives::*,
     versions::{
         Legacy, Modern, ModernWithEntrySize, ModernWithPermissions, ReadVersionSpecificData,
+        WriteVersionSpecificData,
     },
 };
 use crate::deserialize_primitives::*;
 use crate::read_error::{DbFileParseError, ParseErrorKind, ParseFileResult};
-use chrono::NaiveDate;
+use crate::serialize_primitives::*;
+use chrono::NaiveDateTime;
 use crossbeam_utils::thread::{self, Scope, ScopedJoinHandle};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 /// osu!.db struct according to documentation linked in README.
@@ -17,7 +20,9 @@ pub struct OsuDb<'a> {
     pub version: i32,
     pub folder_count: i32,
     pub account_unlocked: bool,
-    pub account_unlock_date: Option<NaiveDate>,
+    /// Only meaningful while the account is locked, but always present in the file and kept as
+    /// read so it's written back unchanged.
+    pub account_unlock_date: NaiveDateTime,
     pub player_name: Option<&'a str>,
     pub number_of_beatmaps: i32,
     pub beatmaps: Vec<Beatmap<'a>>,
@@ -38,12 +43,7 @@ impl<'a> OsuDb<'a> {
         let version = read_int(&bytes, &mut index)?;
         let folder_count = read_int(&bytes, &mut index)?;
         let account_unlocked = read_boolean(&bytes, &mut index)?;
-        let account_unlock_date = if !account_unlocked {
-            Some(read_datetime(&bytes, &mut index)?)
-        } else {
-            let _ = read_long(&bytes, &mut index)?;
-            None
-        };
+        let account_unlock_date = read_datetime(&bytes, &mut index)?;
         let player_name = read_str_utf8(&bytes, &mut index, "player name")?;
         let num_beatmaps = read_int(&bytes, &mut index)?;
         let mut beatmaps = Vec::with_capacity(num_beatmaps as usize);
@@ -64,7 +64,7 @@ impl<'a> OsuDb<'a> {
                     &bytes, &mut index,
                 )?);
             }
-        } else if version > 20191107 {
+        } else if version >= 20191107 {
             for _ in 0..num_beatmaps {
                 beatmaps.push(Beatmap::read_from_bytes::<ModernWithPermissions>(
                     &bytes, &mut index,
@@ -107,12 +107,7 @@ impl<'a> OsuDb<'a> {
         let version = read_int(&bytes, i)?;
         let folder_count = read_int(&bytes, i)?;
         let account_unlocked = read_boolean(&bytes, i)?;
-        let account_unlock_date = if !account_unlocked {
-            Some(read_datetime(&bytes, i)?)
-        } else {
-            let _ = read_datetime(&bytes, i)?;
-            None
-        };
+        let account_unlock_date = read_datetime(&bytes, i)?;
         let player_name = read_player_name(&bytes, i)?;
         let num_beatmaps = read_int(&bytes, i)?;
         let counter = Arc::new(Mutex::new(0));
@@ -180,6 +175,9 @@ impl<'a> OsuDb<'a> {
                 err_msg.as_str(),
             ));
         };
+        // The loader threads advance the shared start index past every entry, so pick up from
+        // there for the rest of the file.
+        index = *start.lock().unwrap();
         let unknown_short_or_permissions = if version < 20140609 {
             Legacy::read_unknown_short_or_user_permissions(&bytes, &mut index)?
         } else if version < 20160408 {
@@ -201,15 +199,49 @@ impl<'a> OsuDb<'a> {
         })
     }
 
+    /// Write the database in the layout used by its version. The number of beatmaps is taken from
+    /// `beatmaps` rather than `number_of_beatmaps`.
+    pub fn write_to_bytes(&self) -> Vec<u8> {
+        if self.version < 20140609 {
+            self.write_version_specific::<Legacy>()
+        } else if self.version < 20160408 {
+            self.write_version_specific::<Modern>()
+        } else if self.version < 20191107 {
+            self.write_version_specific::<ModernWithEntrySize>()
+        } else {
+            self.write_version_specific::<ModernWithPermissions>()
+        }
+    }
+
+    fn write_version_specific<T: WriteVersionSpecificData>(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_int(self.version, &mut bytes);
+        write_int(self.folder_count, &mut bytes);
+        write_boolean(self.account_unlocked, &mut bytes);
+        write_datetime(self.account_unlock_date, &mut bytes);
+        write_str_utf8(self.player_name, &mut bytes);
+        write_int(self.beatmaps.len() as i32, &mut bytes);
+        for beatmap in &self.beatmaps {
+            beatmap.write_to_bytes::<T>(&mut bytes);
+        }
+        T::write_unknown_short_or_user_permissions(self.unknown_short_or_permissions, &mut bytes);
+        bytes
+    }
+
+    /// Index the beatmaps by their MD5 hash, which is how collection.db and scores.db refer to them.
+    pub fn beatmaps_by_hash(&self) -> HashMap<&'a str, &Beatmap<'a>> {
+        self.beatmaps
+            .iter()
+            .map(|beatmap| (beatmap.md5_beatmap_hash, beatmap))
+            .collect()
+    }
+
     pub fn display(&self) {
         println!("version: {}", self.version);
         println!("folder count: {}", self.folder_count);
         println!("account unlocked: {}", self.account_unlocked);
-        if self.account_unlock_date.is_some() {
-            println!(
-                "account unlock date: {}",
-                self.account_unlock_date.as_ref().unwrap()
-            );
+        if !self.account_unlocked {
+            println!("account unlock date: {}", self.account_unlock_date);
         } else {
             println!("account unlock date:");
         }
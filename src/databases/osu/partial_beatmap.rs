@@ -32,13 +32,13 @@ pub struct PartialBeatmap<'a> {
     pub overall_difficulty: Option<ByteSingle>,
     pub slider_velocity: Option<f64>,
     pub num_mod_combo_star_ratings_standard: Option<i32>,
-    pub mod_combo_star_ratings_standard: Option<Vec<(i32, f64)>>,
+    pub mod_combo_star_ratings_standard: Option<Vec<(Mods, f64)>>,
     pub num_mod_combo_star_ratings_taiko: Option<i32>,
-    pub mod_combo_star_ratings_taiko: Option<Vec<(i32, f64)>>,
+    pub mod_combo_star_ratings_taiko: Option<Vec<(Mods, f64)>>,
     pub num_mod_combo_star_ratings_ctb: Option<i32>,
-    pub mod_combo_star_ratings_ctb: Option<Vec<(i32, f64)>>,
+    pub mod_combo_star_ratings_ctb: Option<Vec<(Mods, f64)>>,
     pub num_mod_combo_star_ratings_mania: Option<i32>,
-    pub mod_combo_star_ratings_mania: Option<Vec<(i32, f64)>>,
+    pub mod_combo_star_ratings_mania: Option<Vec<(Mods, f64)>>,
     pub drain_time: Option<i32>,
     pub total_time: Option<i32>,
     pub preview_offset_from_start_ms: Option<i32>,
@@ -344,7 +344,7 @@ impl<'a> PartialBeatmap<'a> {
         if show.mod_combo_star_ratings_standard && self.mod_combo_star_ratings_standard.is_some() {
             println!("    MCSR osu!standard {{");
             for (mods, star_rating) in self.mod_combo_star_ratings_standard.as_ref().unwrap() {
-                println!("        mods: {:32b} | star rating: {}*", mods, star_rating);
+                println!("        mods: {} | star rating: {}*", mods, star_rating);
             }
             println!("    }}");
         }
@@ -356,7 +356,7 @@ impl<'a> PartialBeatmap<'a> {
         if show.mod_combo_star_ratings_taiko && self.mod_combo_star_ratings_taiko.is_some() {
             println!("    MCSR osu!taiko {{");
             for (mods, star_rating) in self.mod_combo_star_ratings_taiko.as_ref().unwrap() {
-                println!("        mods: {:32b} | star rating: {}*", mods, star_rating);
+                println!("        mods: {} | star rating: {}*", mods, star_rating);
             }
             println!("    }}");
         }
@@ -368,7 +368,7 @@ impl<'a> PartialBeatmap<'a> {
         if show.mod_combo_star_ratings_ctb && self.mod_combo_star_ratings_ctb.is_some() {
             println!("    MCSR osu!ctb {{");
             for (mods, star_rating) in self.mod_combo_star_ratings_ctb.as_ref().unwrap() {
-                println!("        mods: {:32b} | star rating: {}*", mods, star_rating);
+                println!("        mods: {} | star rating: {}*", mods, star_rating);
             }
             println!("    }}");
         }
@@ -380,7 +380,7 @@ impl<'a> PartialBeatmap<'a> {
         if show.mod_combo_star_ratings_mania && self.mod_combo_star_ratings_mania.is_some() {
             println!("    MCSR osu!mania {{");
             for (mods, star_rating) in self.mod_combo_star_ratings_mania.as_ref().unwrap() {
-                println!("        mods: {:32b} | star rating: {}*", mods, star_rating);
+                println!("        mods: {} | star rating: {}*", mods, star_rating);
             }
             println!("    }}");
         }
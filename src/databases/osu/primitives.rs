@@ -1,9 +1,11 @@
 use crate::deserialize_primitives::*;
-use crate::load_settings::EqualCopy;
+use crate::load_settings::{EqualCopy, ModsFilter};
 use crate::read_error::{DbFileParseError, ParseErrorKind::*, ParseFileResult};
+use crate::serialize_primitives::*;
 use std::cmp::{Ordering, PartialEq, PartialOrd};
 use std::fmt::{self, Display};
 use std::io::{Error as IoError, ErrorKind::InvalidInput, Result as IoResult};
+use std::ops::BitOr;
 use std::str::FromStr;
 
 // Deserializing osu!.db-specific data types
@@ -19,11 +21,11 @@ macro_rules! primitive {
 /// Read an int-double pair from a slice. The integer represents the mods used and the double
 /// represents the star rating.
 #[inline]
-pub fn read_int_double_pair(bytes: &[u8], i: &mut usize) -> ParseFileResult<(i32, f64)> {
+pub fn read_int_double_pair(bytes: &[u8], i: &mut usize) -> ParseFileResult<(Mods, f64)> {
     let int = read_int(&bytes[*i + 1..*i + 5], &mut 0)?;
     let double = read_double(&bytes[*i + 6..*i + 14], &mut 0)?;
     *i += 14;
-    Ok((int, double))
+    Ok((Mods(int), double))
 }
 
 /// Conditionally read an int-double pair from a slice. The integer represents the mods used and the
@@ -32,18 +34,28 @@ pub fn maybe_read_int_double_pair(
     c: bool,
     bytes: &[u8],
     i: &mut usize,
-) -> ParseFileResult<Option<(i32, f64)>> {
+) -> ParseFileResult<Option<(Mods, f64)>> {
     if c {
         let int = read_int(&bytes[*i + 1..*i + 5], &mut 0)?;
         let double = read_double(&bytes[*i + 6..*i + 14], &mut 0)?;
         *i += 14;
-        Ok(Some((int, double)))
+        Ok(Some((Mods(int), double)))
     } else {
         *i += 14;
         Ok(None)
     }
 }
 
+/// Write an int-double pair. Each of the two values is preceded by its own indicator byte: `0x08`
+/// for the int and `0x0d` for the double.
+#[inline]
+pub fn write_int_double_pair((mods, double): (Mods, f64), bytes: &mut Vec<u8>) {
+    write_byte(0x08, bytes);
+    mods.write_to_bytes(bytes);
+    write_byte(0x0d, bytes);
+    write_double(double, bytes);
+}
+
 /// `TimingPoint`s indicate the BPM of a beatmap at and after a certain offset from the start.
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub struct TimingPoint {
@@ -76,6 +88,28 @@ impl TimingPoint {
             ))
         }
     }
+
+    /// The BPM this timing point sets. Timing points that only change the slider velocity store a
+    /// negative percentage instead of the milliseconds per beat, so they don't have one.
+    pub fn bpm(&self) -> Option<f64> {
+        if self.bpm > 0.0 {
+            Some(60000.0 / self.bpm)
+        } else {
+            None
+        }
+    }
+
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    /// Write a `TimingPoint` to a buffer.
+    #[inline]
+    pub fn write_to_bytes(&self, bytes: &mut Vec<u8>) {
+        write_double(self.bpm, bytes);
+        write_double(self.offset, bytes);
+        write_boolean(self.inherited, bytes);
+    }
 }
 
 impl Display for TimingPoint {
@@ -227,6 +261,23 @@ impl RankedStatus {
             ))
         }
     }
+
+    #[inline]
+    pub fn write_to_bytes(&self, bytes: &mut Vec<u8>) {
+        write_byte(
+            match self {
+                Unknown => 0,
+                Unsubmitted => 1,
+                PendingWIPGraveyard => 2,
+                Unused => 3,
+                Ranked => 4,
+                Approved => 5,
+                Qualified => 6,
+                Loved => 7,
+            },
+            bytes,
+        );
+    }
 }
 
 /// Some database fields will be a byte or a single depending on the version. Since I don't want to
@@ -268,7 +319,7 @@ impl PartialOrd for ByteSingle {
         match (self, other) {
             (&Byte(b0), Byte(b1)) => Some(b0.cmp(b1)),
             (&Byte(b), Single(s)) => (b as f32).partial_cmp(s),
-            (Single(s), &Byte(b)) => (b as f32).partial_cmp(s),
+            (Single(s), &Byte(b)) => s.partial_cmp(&(b as f32)),
             (&Single(s0), Single(s1)) => s0.partial_cmp(s1),
         }
     }
@@ -398,6 +449,20 @@ impl GameplayMode {
             ))
         }
     }
+
+    /// Write a `GameplayMode` to a buffer.
+    #[inline]
+    pub fn write_to_bytes(&self, bytes: &mut Vec<u8>) {
+        write_byte(
+            match self {
+                Standard => 0,
+                Taiko => 1,
+                Ctb => 2,
+                Mania => 3,
+            },
+            bytes,
+        );
+    }
 }
 
 impl Display for GameplayMode {
@@ -415,6 +480,202 @@ impl Display for GameplayMode {
     }
 }
 
+/// The mods a score was set with, or that a star rating was calculated for. osu! stores them as a
+/// bitfield with a bit for each mod. Nightcore is stored along with double time, and perfect along
+/// with sudden death, since they're variants of those mods.
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Mods(pub i32);
+
+impl Mods {
+    pub const NONE: Mods = Mods(0);
+    pub const NO_FAIL: Mods = Mods(1);
+    pub const EASY: Mods = Mods(1 << 1);
+    pub const TOUCH_DEVICE: Mods = Mods(1 << 2);
+    pub const HIDDEN: Mods = Mods(1 << 3);
+    pub const HARD_ROCK: Mods = Mods(1 << 4);
+    pub const SUDDEN_DEATH: Mods = Mods(1 << 5);
+    pub const DOUBLE_TIME: Mods = Mods(1 << 6);
+    pub const RELAX: Mods = Mods(1 << 7);
+    pub const HALF_TIME: Mods = Mods(1 << 8);
+    /// Always stored along with `DOUBLE_TIME`.
+    pub const NIGHTCORE: Mods = Mods(1 << 9);
+    pub const FLASHLIGHT: Mods = Mods(1 << 10);
+    pub const AUTOPLAY: Mods = Mods(1 << 11);
+    pub const SPUN_OUT: Mods = Mods(1 << 12);
+    pub const AUTOPILOT: Mods = Mods(1 << 13);
+    /// Always stored along with `SUDDEN_DEATH`.
+    pub const PERFECT: Mods = Mods(1 << 14);
+    pub const KEY_4: Mods = Mods(1 << 15);
+    pub const KEY_5: Mods = Mods(1 << 16);
+    pub const KEY_6: Mods = Mods(1 << 17);
+    pub const KEY_7: Mods = Mods(1 << 18);
+    pub const KEY_8: Mods = Mods(1 << 19);
+    pub const FADE_IN: Mods = Mods(1 << 20);
+    pub const RANDOM: Mods = Mods(1 << 21);
+    pub const CINEMA: Mods = Mods(1 << 22);
+    pub const TARGET_PRACTICE: Mods = Mods(1 << 23);
+    pub const KEY_9: Mods = Mods(1 << 24);
+    pub const KEY_COOP: Mods = Mods(1 << 25);
+    pub const KEY_1: Mods = Mods(1 << 26);
+    pub const KEY_3: Mods = Mods(1 << 27);
+    pub const KEY_2: Mods = Mods(1 << 28);
+    pub const SCORE_V2: Mods = Mods(1 << 29);
+    pub const MIRROR: Mods = Mods(1 << 30);
+
+    /// Every mod along with its acronym, in the order osu! shows them.
+    const ACRONYMS: [(&'static str, Mods); 31] = [
+        ("NF", Mods::NO_FAIL),
+        ("EZ", Mods::EASY),
+        ("TD", Mods::TOUCH_DEVICE),
+        ("HD", Mods::HIDDEN),
+        ("HR", Mods::HARD_ROCK),
+        ("SD", Mods::SUDDEN_DEATH),
+        ("DT", Mods::DOUBLE_TIME),
+        ("RX", Mods::RELAX),
+        ("HT", Mods::HALF_TIME),
+        ("NC", Mods::NIGHTCORE),
+        ("FL", Mods::FLASHLIGHT),
+        ("AT", Mods::AUTOPLAY),
+        ("SO", Mods::SPUN_OUT),
+        ("AP", Mods::AUTOPILOT),
+        ("PF", Mods::PERFECT),
+        ("4K", Mods::KEY_4),
+        ("5K", Mods::KEY_5),
+        ("6K", Mods::KEY_6),
+        ("7K", Mods::KEY_7),
+        ("8K", Mods::KEY_8),
+        ("FI", Mods::FADE_IN),
+        ("RD", Mods::RANDOM),
+        ("CN", Mods::CINEMA),
+        ("TP", Mods::TARGET_PRACTICE),
+        ("9K", Mods::KEY_9),
+        ("CP", Mods::KEY_COOP),
+        ("1K", Mods::KEY_1),
+        ("3K", Mods::KEY_3),
+        ("2K", Mods::KEY_2),
+        ("V2", Mods::SCORE_V2),
+        ("MR", Mods::MIRROR),
+    ];
+
+    /// Whether every mod in `other` is in `self`.
+    pub fn contains(self, other: Mods) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether any mod in `other` is in `self`.
+    pub fn intersects(self, other: Mods) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Only the mods that change star ratings, which are the ones osu! keys its precalculated star
+    /// ratings by. Nightcore counts as the double time it's stored along with.
+    pub fn difficulty_mods(self) -> Mods {
+        let difficulty = Mods::EASY | Mods::HARD_ROCK | Mods::DOUBLE_TIME | Mods::HALF_TIME;
+        Mods(self.0 & difficulty.0)
+    }
+
+    #[inline]
+    pub fn read_from_bytes(bytes: &[u8], i: &mut usize) -> ParseFileResult<Self> {
+        Ok(Mods(read_int(bytes, i)?))
+    }
+
+    /// Conditionally parse `Mods` from a slice of bytes.
+    #[inline]
+    pub fn maybe_read_from_bytes(
+        setting: ModsFilter,
+        skip: &mut bool,
+        bytes: &[u8],
+        i: &mut usize,
+    ) -> ParseFileResult<Option<Self>> {
+        let mods = Mods::read_from_bytes(bytes, i)?;
+        if *skip || setting.is_ignore() {
+            Ok(None)
+        } else if setting.matches(mods) {
+            Ok(Some(mods))
+        } else {
+            *skip = true;
+            Ok(None)
+        }
+    }
+
+    #[inline]
+    pub fn write_to_bytes(self, bytes: &mut Vec<u8>) {
+        write_int(self.0, bytes);
+    }
+}
+
+impl BitOr for Mods {
+    type Output = Mods;
+
+    fn bitor(self, other: Mods) -> Mods {
+        Mods(self.0 | other.0)
+    }
+}
+
+/// Acronyms, e.g. "HDHR", or "NM" for no mods. Nightcore and perfect hide the mods they're stored
+/// along with.
+impl Display for Mods {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if *self == Mods::NONE {
+            return write!(f, "NM");
+        }
+        let mut known = Mods::NONE;
+        for &(acronym, mods) in Mods::ACRONYMS.iter() {
+            known = known | mods;
+            let implied = (mods == Mods::DOUBLE_TIME && self.contains(Mods::NIGHTCORE))
+                || (mods == Mods::SUDDEN_DEATH && self.contains(Mods::PERFECT));
+            if self.contains(mods) && !implied {
+                write!(f, "{}", acronym)?;
+            }
+        }
+        // Bits that don't belong to any mod, so that nothing is hidden.
+        let unknown = self.0 & !known.0;
+        if unknown != 0 {
+            write!(f, "+{}", unknown)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses acronyms in any case, optionally separated by spaces, commas, or plus signs, e.g.
+/// "HDHR", "hd,hr", or "+HD +HR". "NM" means no mods, and a number is taken as the raw bitfield.
+/// "NC" and "PF" add the mods they're stored along with.
+impl FromStr for Mods {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        if let Ok(bits) = s.trim().parse::<i32>() {
+            return Ok(Mods(bits));
+        }
+        let acronyms = s
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != ',' && *c != '+')
+            .collect::<String>()
+            .to_uppercase();
+        if acronyms == "NM" || acronyms == "NOMOD" {
+            return Ok(Mods::NONE);
+        }
+        if acronyms.is_empty() || acronyms.len() % 2 != 0 || !acronyms.is_ascii() {
+            return Err(format!("Invalid mods: {}", s));
+        }
+        let mut parsed = Mods::NONE;
+        for k in (0..acronyms.len()).step_by(2) {
+            let acronym = &acronyms[k..k + 2];
+            let mods = match Mods::ACRONYMS.iter().find(|&&(a, _)| a == acronym) {
+                Some(&(_, mods)) => mods,
+                None => return Err(format!("Unknown mod: {}", acronym)),
+            };
+            parsed = parsed | mods;
+            if mods == Mods::NIGHTCORE {
+                parsed = parsed | Mods::DOUBLE_TIME;
+            } else if mods == Mods::PERFECT {
+                parsed = parsed | Mods::SUDDEN_DEATH;
+            }
+        }
+        Ok(parsed)
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum UserPermissions {
     None,
@@ -424,7 +685,8 @@ pub enum UserPermissions {
     Friend,
     peppy,
     WorldCupStaff,
-    Invalid,
+    /// Holds the raw value so that it can be written back unchanged.
+    Invalid(i32),
 }
 
 impl UserPermissions {
@@ -437,7 +699,21 @@ impl UserPermissions {
             8 => UserPermissions::Friend,
             16 => UserPermissions::peppy,
             32 => UserPermissions::WorldCupStaff,
-            _ => UserPermissions::Invalid,
+            n => UserPermissions::Invalid(n),
+        }
+    }
+
+    /// Inverse of `new`.
+    pub(crate) fn to_int(self) -> i32 {
+        match self {
+            UserPermissions::None => 0,
+            UserPermissions::Normal => 1,
+            UserPermissions::Moderator => 2,
+            UserPermissions::Supporter => 4,
+            UserPermissions::Friend => 8,
+            UserPermissions::peppy => 16,
+            UserPermissions::WorldCupStaff => 32,
+            UserPermissions::Invalid(n) => n,
         }
     }
 
@@ -446,6 +722,11 @@ impl UserPermissions {
         let int = read_int(bytes, i)?;
         Ok(UserPermissions::new(int))
     }
+
+    #[inline]
+    pub fn write_to_bytes(&self, bytes: &mut Vec<u8>) {
+        write_int(self.to_int(), bytes);
+    }
 }
 
 impl Display for UserPermissions {
@@ -461,7 +742,7 @@ impl Display for UserPermissions {
                 UserPermissions::Friend => "Friend",
                 UserPermissions::peppy => "peppy",
                 UserPermissions::WorldCupStaff => "World Cup staff",
-                UserPermissions::Invalid => "Invalid",
+                UserPermissions::Invalid(_) => "Invalid",
             }
         )
     }
@@ -1,12 +1,13 @@
 use crate::databases::osu::primitives::{
-    maybe_read_int_double_pair, read_int_double_pair,
+    maybe_read_int_double_pair, read_int_double_pair, write_int_double_pair,
     ByteSingle::{self, *},
-    UnknownShortOrUserPermissions, UserPermissions,
+    Mods, UnknownShortOrUserPermissions, UserPermissions,
 };
 use crate::deserialize_primitives::*;
 use crate::load_settings::Relational;
 use crate::maybe_deserialize_primitives::*;
 use crate::read_error::{DbFileParseError, ParseErrorKind, ParseFileResult};
+use crate::serialize_primitives::*;
 
 /// Covers versions `..20140609`.
 #[derive(Clone, Copy, Debug)]
@@ -44,7 +45,7 @@ pub trait ReadVersionSpecificData {
     fn read_mod_combo_star_ratings(
         _bytes: &[u8],
         _i: &mut usize,
-    ) -> ParseFileResult<(Option<i32>, Option<Vec<(i32, f64)>>)> {
+    ) -> ParseFileResult<(Option<i32>, Option<Vec<(Mods, f64)>>)> {
         Ok((None, None))
     }
 
@@ -92,7 +93,7 @@ impl ReadVersionSpecificData for Modern {
     fn read_mod_combo_star_ratings(
         bytes: &[u8],
         i: &mut usize,
-    ) -> ParseFileResult<(Option<i32>, Option<Vec<(i32, f64)>>)> {
+    ) -> ParseFileResult<(Option<i32>, Option<Vec<(Mods, f64)>>)> {
         // Present in `Modern`.
         let num_int_doubles = read_int(bytes, i)?;
         let mut int_double_pairs = Vec::with_capacity(num_int_doubles as usize);
@@ -120,7 +121,7 @@ impl ReadVersionSpecificData for ModernWithEntrySize {
     fn read_mod_combo_star_ratings(
         bytes: &[u8],
         i: &mut usize,
-    ) -> ParseFileResult<(Option<i32>, Option<Vec<(i32, f64)>>)> {
+    ) -> ParseFileResult<(Option<i32>, Option<Vec<(Mods, f64)>>)> {
         // Present in `ModernWithEntrySize`.
         let num_int_doubles = read_int(bytes, i)?;
         let mut int_double_pairs = Vec::with_capacity(num_int_doubles as usize);
@@ -142,7 +143,7 @@ impl ReadVersionSpecificData for ModernWithPermissions {
     fn read_mod_combo_star_ratings(
         bytes: &[u8],
         i: &mut usize,
-    ) -> ParseFileResult<(Option<i32>, Option<Vec<(i32, f64)>>)> {
+    ) -> ParseFileResult<(Option<i32>, Option<Vec<(Mods, f64)>>)> {
         // Present in `ModernWithPermissions`.
         let num_int_doubles = read_int(bytes, i)?;
         let mut int_double_pairs = Vec::with_capacity(num_int_doubles as usize);
@@ -193,7 +194,7 @@ pub trait ReadPartialVersionSpecificData {
         _skip: &mut bool,
         _bytes: &[u8],
         _i: &mut usize,
-    ) -> ParseFileResult<(Option<i32>, Option<Vec<(i32, f64)>>)> {
+    ) -> ParseFileResult<(Option<i32>, Option<Vec<(Mods, f64)>>)> {
         Ok((None, None))
     }
 
@@ -279,7 +280,7 @@ impl ReadPartialVersionSpecificData for Modern {
         skip: &mut bool,
         bytes: &[u8],
         i: &mut usize,
-    ) -> ParseFileResult<(Option<i32>, Option<Vec<(i32, f64)>>)> {
+    ) -> ParseFileResult<(Option<i32>, Option<Vec<(Mods, f64)>>)> {
         let num_int_doubles = read_int(bytes, i)?;
         if *i + num_int_doubles as usize * 14 < bytes.len() {
             if *skip {
@@ -349,7 +350,7 @@ impl ReadPartialVersionSpecificData for ModernWithEntrySize {
         skip: &mut bool,
         bytes: &[u8],
         i: &mut usize,
-    ) -> ParseFileResult<(Option<i32>, Option<Vec<(i32, f64)>>)> {
+    ) -> ParseFileResult<(Option<i32>, Option<Vec<(Mods, f64)>>)> {
         let num_int_doubles = read_int(bytes, i)?;
         if *i + num_int_doubles as usize * 14 < bytes.len() {
             if *skip {
@@ -409,7 +410,7 @@ impl ReadPartialVersionSpecificData for ModernWithPermissions {
         skip: &mut bool,
         bytes: &[u8],
         i: &mut usize,
-    ) -> ParseFileResult<(Option<i32>, Option<Vec<(i32, f64)>>)> {
+    ) -> ParseFileResult<(Option<i32>, Option<Vec<(Mods, f64)>>)> {
         let num_int_doubles = read_int(bytes, i)?;
         if *i + num_int_doubles as usize * 14 < bytes.len() {
             if *skip || (num_setting && mcsr_setting) {
@@ -473,3 +474,256 @@ impl ReadPartialVersionSpecificData for ModernWithPermissions {
         }
     }
 }
+
+/// The writing counterpart to `ReadVersionSpecificData`. Writing is infallible, so rather than
+/// erroring on data that doesn't fit the target version, fields are converted to whatever the
+/// version expects (e.g. a `Single` is truncated to a `Byte` for `Legacy`).
+pub trait WriteVersionSpecificData {
+    /// Only `ModernWithEntrySize` prefixes each beatmap entry with its size in bytes, which doesn't
+    /// include the four bytes of the size itself.
+    #[inline]
+    fn write_sized_entry(entry: &[u8], bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(entry);
+    }
+
+    /// `Legacy` uses bytes for AR, CS, HP, and OD. The modern versions use `single`s (`f32`s).
+    #[inline]
+    fn write_arcshpod(arcshpod: ByteSingle, bytes: &mut Vec<u8>) {
+        write_single(f32::from(arcshpod), bytes);
+    }
+
+    /// Missing in `Legacy`. The number of int-double pairs is taken from the pairs themselves.
+    #[inline]
+    fn write_mod_combo_star_ratings(
+        mod_combo_star_ratings: Option<&Vec<(Mods, f64)>>,
+        bytes: &mut Vec<u8>,
+    ) {
+        let mod_combo_star_ratings = mod_combo_star_ratings.map(Vec::as_slice).unwrap_or(&[]);
+        write_int(mod_combo_star_ratings.len() as i32, bytes);
+        for &int_double_pair in mod_combo_star_ratings {
+            write_int_double_pair(int_double_pair, bytes);
+        }
+    }
+
+    /// Only present in `Legacy`.
+    #[inline]
+    fn write_unknown_short(_unknown_short: Option<i16>, _bytes: &mut Vec<u8>) {}
+
+    /// Everything before `ModernWithPermissions` ends with a `short`.
+    #[inline]
+    fn write_unknown_short_or_user_permissions(
+        unknown_short_or_user_permissions: UnknownShortOrUserPermissions,
+        bytes: &mut Vec<u8>,
+    ) {
+        let short = match unknown_short_or_user_permissions {
+            UnknownShortOrUserPermissions::UnknownShort(short) => short,
+            UnknownShortOrUserPermissions::UserPermissions(perms) => perms.to_int() as i16,
+        };
+        write_short(short, bytes);
+    }
+}
+
+impl WriteVersionSpecificData for Legacy {
+    #[inline]
+    fn write_arcshpod(arcshpod: ByteSingle, bytes: &mut Vec<u8>) {
+        write_byte(u8::from(arcshpod), bytes);
+    }
+
+    #[inline]
+    fn write_mod_combo_star_ratings(
+        _mod_combo_star_ratings: Option<&Vec<(Mods, f64)>>,
+        _bytes: &mut Vec<u8>,
+    ) {
+    }
+
+    #[inline]
+    fn write_unknown_short(unknown_short: Option<i16>, bytes: &mut Vec<u8>) {
+        write_short(unknown_short.unwrap_or(0), bytes);
+    }
+}
+
+impl WriteVersionSpecificData for Modern {}
+
+impl WriteVersionSpecificData for ModernWithEntrySize {
+    #[inline]
+    fn write_sized_entry(entry: &[u8], bytes: &mut Vec<u8>) {
+        write_int(entry.len() as i32, bytes);
+        bytes.extend_from_slice(entry);
+    }
+}
+
+impl WriteVersionSpecificData for ModernWithPermissions {
+    #[inline]
+    fn write_unknown_short_or_user_permissions(
+        unknown_short_or_user_permissions: UnknownShortOrUserPermissions,
+        bytes: &mut Vec<u8>,
+    ) {
+        match unknown_short_or_user_permissions {
+            UnknownShortOrUserPermissions::UnknownShort(short) => write_int(short as i32, bytes),
+            UnknownShortOrUserPermissions::UserPermissions(perms) => perms.write_to_bytes(bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::databases::osu::osudb::OsuDb;
+    use crate::serialize_primitives::*;
+
+    /// A beatmap entry laid out the way `version` stores it, without the entry size.
+    fn beatmap_entry(version: i32, hash: &str, beatmap_id: i32) -> Vec<u8> {
+        let modern = version >= 20140609;
+        let mut e = Vec::new();
+        write_str_utf8(Some("Artist"), &mut e);
+        write_str_utf8(None, &mut e);
+        write_str_utf8(Some("Title"), &mut e);
+        write_str_utf8(Some("タイトル"), &mut e);
+        write_player_name(Some("Mapper"), &mut e);
+        write_str_utf8(Some("Insane"), &mut e);
+        write_str_utf8(Some("audio.mp3"), &mut e);
+        write_md5_hash(hash, &mut e);
+        write_str_utf8(Some("map.osu"), &mut e);
+        write_byte(4, &mut e); // ranked
+        write_short(300, &mut e);
+        write_short(150, &mut e);
+        write_short(2, &mut e);
+        write_long(637_000_000_123_456_789, &mut e);
+        for &stat in &[9.3, 4.2, 6.0, 8.5] {
+            if modern {
+                write_single(stat, &mut e);
+            } else {
+                write_byte(stat as u8, &mut e);
+            }
+        }
+        write_double(1.4, &mut e);
+        if modern {
+            for mode in 0..4 {
+                write_int(mode, &mut e);
+                for n in 0..mode {
+                    write_byte(0x08, &mut e);
+                    write_int(1 << n, &mut e);
+                    write_byte(0x0d, &mut e);
+                    write_double(5.25 + n as f64, &mut e);
+                }
+            }
+        }
+        write_int(180, &mut e);
+        write_int(185_000, &mut e);
+        write_int(40_000, &mut e);
+        write_int(2, &mut e);
+        for &(bpm, offset, inherited) in &[(333.33, 120.0, true), (-100.0, 5000.0, false)] {
+            write_double(bpm, &mut e);
+            write_double(offset, &mut e);
+            write_boolean(inherited, &mut e);
+        }
+        write_int(beatmap_id, &mut e);
+        write_int(1000, &mut e);
+        write_int(0, &mut e);
+        for &grade in &[0, 9, 9, 9] {
+            write_byte(grade, &mut e);
+        }
+        write_short(-5, &mut e);
+        write_single(0.7, &mut e);
+        write_byte(0, &mut e); // standard
+        write_str_utf8(Some("Source"), &mut e);
+        write_str_utf8(Some("tags go here"), &mut e);
+        write_short(0, &mut e);
+        write_str_utf8(None, &mut e);
+        write_boolean(false, &mut e);
+        write_long(637_100_000_000_000_000, &mut e);
+        write_boolean(false, &mut e);
+        write_str_utf8(Some("1000 Artist - Title"), &mut e);
+        write_long(637_200_000_000_000_000, &mut e);
+        for &flag in &[false, true, false, true, false] {
+            write_boolean(flag, &mut e);
+        }
+        if !modern {
+            write_short(7, &mut e);
+        }
+        write_int(12_345, &mut e);
+        write_byte(20, &mut e);
+        e
+    }
+
+    /// An osu!.db with two beatmaps laid out the way `version` stores it. `entry_size` gives the
+    /// size to prefix each entry with, where the version has one.
+    fn osu_db(version: i32, entry_size: impl Fn(&[u8]) -> i32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_int(version, &mut bytes);
+        write_int(40, &mut bytes);
+        write_boolean(true, &mut bytes);
+        // Meaningless while the account is unlocked, but still has to be written back as is.
+        write_long(636_000_000_000_000_001, &mut bytes);
+        write_str_utf8(Some("Player"), &mut bytes);
+        write_int(2, &mut bytes);
+        let hashes = [
+            "0123456789abcdef0123456789abcdef",
+            "fedcba9876543210fedcba9876543210",
+        ];
+        for (n, hash) in hashes.iter().enumerate() {
+            let entry = beatmap_entry(version, hash, n as i32 + 1);
+            if (20160408..20191107).contains(&version) {
+                write_int(entry_size(&entry), &mut bytes);
+            }
+            bytes.extend_from_slice(&entry);
+        }
+        if version < 20191107 {
+            write_short(3, &mut bytes);
+        } else {
+            write_int(4, &mut bytes); // supporter
+        }
+        bytes
+    }
+
+    fn assert_round_trips(version: i32) {
+        let bytes = osu_db(version, |entry| entry.len() as i32);
+        // Only `ModernWithEntrySize` can be loaded on several threads, since the threads rely on
+        // the entry sizes to find the beatmaps.
+        let jobs: &[usize] = if (20160408..20191107).contains(&version) {
+            &[1, 2]
+        } else {
+            &[1]
+        };
+        for &jobs in jobs {
+            let osu_db = OsuDb::read_from_bytes(jobs, &bytes).expect("Failed to read osu!.db.");
+            assert_eq!(osu_db.beatmaps.len(), 2);
+            assert_eq!(
+                osu_db.write_to_bytes(),
+                bytes,
+                "version {}, {} jobs",
+                version,
+                jobs
+            );
+        }
+    }
+
+    #[test]
+    fn legacy_round_trips() {
+        assert_round_trips(20140608);
+    }
+
+    #[test]
+    fn modern_round_trips() {
+        assert_round_trips(20140609);
+    }
+
+    #[test]
+    fn modern_with_entry_size_round_trips() {
+        assert_round_trips(20160408);
+    }
+
+    #[test]
+    fn modern_with_permissions_round_trips() {
+        assert_round_trips(20191107);
+    }
+
+    #[test]
+    fn entry_size_is_recomputed() {
+        let stale = osu_db(20160408, |_| 0);
+        let osu_db = OsuDb::read_from_bytes(1, &stale).expect("Failed to read osu!.db.");
+        assert_eq!(
+            osu_db.write_to_bytes(),
+            self::osu_db(20160408, |entry| entry.len() as i32)
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use crate::argument::DbIndicator;
+use crate::databases::{
+    collection_command::save_to_collection,
+    osu::{
+        beatmap::Beatmap,
+        osudb::OsuDb,
+        primitives::{ByteSingle, GameplayMode, Mods},
+    },
+};
+use crate::load_settings::Relational;
+use crate::output::Backups;
+use structopt::StructOpt;
+
+// A collection of beatmaps ordered from easiest to hardest, with star ratings spaced as evenly as
+// the beatmaps in osu!.db allow.
+#[derive(StructOpt)]
+pub struct Playlist {
+    #[structopt(long = "min-stars", value_name = "STARS")]
+    min_stars: f64,
+    #[structopt(long = "max-stars", value_name = "STARS")]
+    max_stars: f64,
+    #[structopt(
+        short = "n",
+        long = "count",
+        value_name = "NUM",
+        default_value = "10",
+        help = "Number of beatmaps in the playlist. Fewer are used if fewer match."
+    )]
+    count: usize,
+    #[structopt(
+        name = "gameplay mode",
+        long = "mode",
+        value_name = "MODE",
+        default_value = "standard",
+        possible_values(&["standard", "taiko", "ctb", "mania"]),
+        parse(try_from_str)
+    )]
+    gameplay_mode: GameplayMode,
+    #[structopt(
+        long = "mods",
+        value_name = "MODS",
+        default_value = "NM",
+        parse(try_from_str),
+        help = "Mods whose star ratings are used, e.g. HR or DT. osu! only calculates star ratings \
+                for some combinations of mods."
+    )]
+    mods: Mods,
+    #[structopt(
+        name = "approach rate",
+        long = "ar",
+        value_name = "RELATIONAL",
+        default_value,
+        parse(try_from_str)
+    )]
+    approach_rate: Relational<ByteSingle>,
+    #[structopt(
+        long = "bpm",
+        value_name = "RELATIONAL",
+        default_value,
+        parse(try_from_str),
+        help = "BPM, as shown in osu! for beatmaps whose BPM changes"
+    )]
+    bpm: Relational<f64>,
+    #[structopt(
+        name = "drain time",
+        long = "drain-time",
+        value_name = "RELATIONAL",
+        default_value,
+        parse(try_from_str),
+        help = "Drain time in seconds"
+    )]
+    drain_time: Relational<i32>,
+    #[structopt(
+        name = "collection.db path",
+        long = "collection-db",
+        value_name = "PATH",
+        help = "collection.db to save the playlist to"
+    )]
+    collection_db_path: String,
+    #[structopt(
+        name = "collection name",
+        long = "into",
+        value_name = "NAME",
+        help = "Collection to save the playlist to. It's created if it doesn't exist and its \
+                beatmaps are replaced if it does."
+    )]
+    into: String,
+    #[structopt(flatten)]
+    backups: Backups,
+}
+
+impl Playlist {
+    /// Pick the beatmaps for the playlist from the osu!.db in `bytes` and save them to
+    /// `--into`.
+    pub fn generate(&self, jobs: usize, db_type: DbIndicator, bytes: &[u8]) {
+        if db_type != DbIndicator::OsuDb {
+            println!("Playlists can only be generated from osu!.db.");
+            return;
+        }
+        if self.min_stars > self.max_stars {
+            println!("--min-stars can't be more than --max-stars.");
+            return;
+        }
+        if self.count == 0 {
+            println!("A playlist needs at least one beatmap.");
+            return;
+        }
+        let osu_db = OsuDb::read_from_bytes(jobs, bytes).expect("Failed to parse database.");
+        let mut unrated = 0;
+        let mut candidates = Vec::new();
+        for beatmap in &osu_db.beatmaps {
+            if beatmap.gameplay_mode != self.gameplay_mode || !self.matches(beatmap) {
+                continue;
+            }
+            match star_rating(beatmap, self.mods) {
+                Some(stars) if stars >= self.min_stars && stars <= self.max_stars => {
+                    candidates.push((stars, beatmap))
+                }
+                Some(_) => {}
+                None => unrated += 1,
+            }
+        }
+        if unrated > 0 {
+            println!(
+                "Skipped {} beatmaps without a star rating for mods {}.",
+                unrated, self.mods
+            );
+        }
+        if candidates.is_empty() {
+            println!("No beatmaps match.");
+            return;
+        }
+        let picks = pick_evenly(candidates, self.min_stars, self.max_stars, self.count);
+        println!("Playlist \"{}\":", self.into);
+        for (step, (stars, beatmap)) in picks.iter().enumerate() {
+            println!("  {:>3}. {:.2}* {}", step + 1, stars, beatmap.label());
+        }
+        let hashes = picks
+            .iter()
+            .map(|(_, beatmap)| beatmap.md5_beatmap_hash)
+            .collect();
+        save_to_collection(
+            jobs,
+            &self.collection_db_path,
+            &self.into,
+            hashes,
+            self.backups.count,
+        );
+    }
+
+    fn matches(&self, beatmap: &Beatmap) -> bool {
+        self.approach_rate.matches(&beatmap.approach_rate)
+            && self.drain_time.matches(&beatmap.drain_time)
+            && (self.bpm.is_ignore()
+                || matches!(beatmap.main_bpm(), Some(bpm) if self.bpm.matches(&bpm)))
+    }
+}
+
+/// The precalculated star rating of the beatmap in its own gameplay mode with `mods`, if osu! has
+/// calculated one. Mods that don't change star ratings, like hidden, are left out of the lookup.
+fn star_rating(beatmap: &Beatmap, mods: Mods) -> Option<f64> {
+    let star_ratings = match beatmap.gameplay_mode {
+        GameplayMode::Standard => &beatmap.mod_combo_star_ratings_standard,
+        GameplayMode::Taiko => &beatmap.mod_combo_star_ratings_taiko,
+        GameplayMode::Ctb => &beatmap.mod_combo_star_ratings_ctb,
+        GameplayMode::Mania => &beatmap.mod_combo_star_ratings_mania,
+    };
+    star_ratings
+        .iter()
+        .flatten()
+        .find(|&&(combination, _)| combination == mods.difficulty_mods())
+        .map(|&(_, stars)| stars)
+}
+
+/// Space `count` target star ratings evenly from `min` to `max`, and take the closest beatmap to
+/// each that hasn't been taken yet. The picks are sorted by star rating, so the playlist never
+/// gets easier even if the closest beatmaps overshoot their targets.
+fn pick_evenly<'a, 'b>(
+    mut candidates: Vec<(f64, &'b Beatmap<'a>)>,
+    min: f64,
+    max: f64,
+    count: usize,
+) -> Vec<(f64, &'b Beatmap<'a>)> {
+    let mut picks = Vec::with_capacity(count.min(candidates.len()));
+    for step in 0..count {
+        if candidates.is_empty() {
+            break;
+        }
+        let target = if count == 1 {
+            min
+        } else {
+            min + (max - min) * step as f64 / (count - 1) as f64
+        };
+        let closest = candidates
+            .iter()
+            .enumerate()
+            .min_by(|(_, (a, _)), (_, (b, _))| {
+                (a - target).abs().partial_cmp(&(b - target).abs()).unwrap()
+            })
+            .map(|(index, _)| index)
+            .unwrap();
+        picks.push(candidates.swap_remove(closest));
+    }
+    picks.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap());
+    picks
+}
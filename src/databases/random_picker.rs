@@ -0,0 +1,130 @@
+use crate::argument::DbIndicator;
+use crate::databases::{
+    collection_command::save_to_collection,
+    osu::{beatmap::Beatmap, osudb::OsuDb},
+};
+use crate::load_settings::osu::osudb_load_settings::OsuDbLoadSettings;
+use crate::output::Backups;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use std::collections::HashMap;
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+pub struct Random {
+    #[structopt(
+        short = "n",
+        long = "count",
+        value_name = "NUM",
+        default_value = "1",
+        help = "Number of beatmaps to pick. Fewer are picked if fewer match."
+    )]
+    count: usize,
+    #[structopt(
+        long = "seed",
+        value_name = "SEED",
+        help = "Seed for the picks, so that the same seed picks the same beatmaps from the same \
+                osu!.db."
+    )]
+    seed: Option<u64>,
+    #[structopt(
+        long = "one-per-set",
+        help = "Pick at most one difficulty from each beatmap set."
+    )]
+    one_per_set: bool,
+    #[structopt(
+        name = "collection.db path",
+        long = "collection-db",
+        value_name = "PATH",
+        requires = "collection name",
+        help = "collection.db to save the picks to"
+    )]
+    collection_db_path: Option<String>,
+    #[structopt(
+        name = "collection name",
+        long = "into",
+        value_name = "NAME",
+        requires = "collection.db path",
+        help = "Collection to save the picks to. It's created if it doesn't exist and its beatmaps \
+                are replaced if it does."
+    )]
+    into: Option<String>,
+    #[structopt(flatten)]
+    backups: Backups,
+    #[structopt(flatten)]
+    filter: OsuDbLoadSettings,
+}
+
+impl Random {
+    /// Pick random beatmaps matching the filter from the osu!.db in `bytes`, and save them to a
+    /// collection if asked to.
+    pub fn pick(&self, jobs: usize, db_type: DbIndicator, bytes: &[u8]) {
+        if db_type != DbIndicator::OsuDb {
+            println!("Picking random beatmaps only works on osu!.db.");
+            return;
+        }
+        let osu_db = OsuDb::read_from_bytes(jobs, bytes).expect("Failed to parse database.");
+        let candidates = osu_db
+            .beatmaps
+            .iter()
+            .filter(|beatmap| self.filter.beatmap_load_settings.matches(beatmap))
+            .collect::<Vec<_>>();
+        let mut rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let picks = if self.one_per_set {
+            pick_one_per_set(&candidates, self.count, &mut rng)
+        } else {
+            candidates
+                .choose_multiple(&mut rng, self.count)
+                .copied()
+                .collect()
+        };
+        if picks.is_empty() {
+            println!("No beatmaps match.");
+            return;
+        }
+        println!(
+            "Picked {} of {} matching beatmaps:",
+            picks.len(),
+            candidates.len()
+        );
+        for beatmap in &picks {
+            println!("  {}", beatmap.label());
+        }
+        if let (Some(path), Some(name)) = (&self.collection_db_path, &self.into) {
+            let hashes = picks
+                .iter()
+                .map(|beatmap| beatmap.md5_beatmap_hash)
+                .collect();
+            save_to_collection(jobs, path, name, hashes, self.backups.count);
+        }
+    }
+}
+
+/// Pick `count` sets at random, then a random difficulty from each. Beatmaps without a set ID
+/// count as their own set.
+fn pick_one_per_set<'a, 'b>(
+    candidates: &[&'b Beatmap<'a>],
+    count: usize,
+    rng: &mut StdRng,
+) -> Vec<&'b Beatmap<'a>> {
+    let mut sets: Vec<Vec<&Beatmap>> = Vec::new();
+    let mut positions = HashMap::new();
+    for &beatmap in candidates {
+        if beatmap.beatmap_set_id <= 0 {
+            sets.push(vec![beatmap]);
+            continue;
+        }
+        let position = *positions.entry(beatmap.beatmap_set_id).or_insert_with(|| {
+            sets.push(Vec::new());
+            sets.len() - 1
+        });
+        sets[position].push(beatmap);
+    }
+    let picked_sets = sets.choose_multiple(rng, count).collect::<Vec<_>>();
+    picked_sets
+        .into_iter()
+        .filter_map(|set| set.choose(rng).copied())
+        .collect()
+}
@@ -0,0 +1,326 @@
+use crate::argument::DbIndicator;
+use crate::databases::{
+    collection::collectiondb::CollectionDb,
+    collection_command::read_osu_db,
+    merge::is_same_score,
+    osu::osudb::OsuDb,
+    scores::{scoresdb::ScoresDb, scoresdb_beatmap::ScoresDbBeatmap},
+};
+use crate::output::{write_atomically, Output};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use structopt::StructOpt;
+
+// Updating a beatmap changes its MD5 hash, which leaves collections and scores pointing at a
+// beatmap that's no longer there. The beatmap ID stays the same, so old hashes can be matched up
+// with new ones through it.
+#[derive(StructOpt)]
+pub struct Remap {
+    #[structopt(
+        name = "osu!.db path",
+        long = "osu-db",
+        value_name = "PATH",
+        help = "The current osu!.db, whose hashes the old ones are remapped to"
+    )]
+    osu_db_path: String,
+    #[structopt(
+        name = "old osu!.db path",
+        long = "old-osu-db",
+        value_name = "PATH",
+        required_unless = "mapping path",
+        conflicts_with = "mapping path",
+        help = "An osu!.db from before the beatmaps were updated, to look up the old hashes' \
+                beatmap IDs in"
+    )]
+    old_osu_db_path: Option<String>,
+    #[structopt(
+        name = "mapping path",
+        long = "mapping",
+        value_name = "PATH",
+        help = "A file of old hashes and their beatmap IDs, one pair per line, separated by \
+                whitespace or a comma. Empty lines and lines starting with # are skipped."
+    )]
+    mapping_path: Option<String>,
+    #[structopt(
+        long = "report",
+        value_name = "PATH",
+        help = "Write the hashes that couldn't be remapped to PATH instead of printing them."
+    )]
+    report_path: Option<String>,
+    #[structopt(flatten)]
+    output: Output,
+}
+
+enum HashLookup<'a> {
+    Current,
+    Remapped(&'a str),
+    Unresolved(String),
+}
+
+/// Looks up the current hash of the beatmap an old hash belonged to.
+struct Remapper<'a> {
+    installed: HashSet<&'a str>,
+    current_hashes: HashMap<i32, &'a str>,
+    old_beatmap_ids: HashMap<String, i32>,
+}
+
+impl<'a> Remapper<'a> {
+    fn new(osu_db: &OsuDb<'a>, old_beatmap_ids: HashMap<String, i32>) -> Self {
+        Remapper {
+            installed: osu_db
+                .beatmaps
+                .iter()
+                .map(|beatmap| beatmap.md5_beatmap_hash)
+                .collect(),
+            // Unsubmitted beatmaps all have an ID of 0 or less, so those IDs don't identify them.
+            current_hashes: osu_db
+                .beatmaps
+                .iter()
+                .filter(|beatmap| beatmap.beatmap_id > 0)
+                .map(|beatmap| (beatmap.beatmap_id, beatmap.md5_beatmap_hash))
+                .collect(),
+            old_beatmap_ids,
+        }
+    }
+
+    fn remap(&self, hash: &str) -> HashLookup<'a> {
+        if self.installed.contains(hash) {
+            return HashLookup::Current;
+        }
+        match self.old_beatmap_ids.get(hash) {
+            None => HashLookup::Unresolved("beatmap ID unknown".to_string()),
+            Some(&beatmap_id) if beatmap_id <= 0 => {
+                HashLookup::Unresolved(format!("unsubmitted beatmap (ID {})", beatmap_id))
+            }
+            Some(beatmap_id) => match self.current_hashes.get(beatmap_id) {
+                Some(&current_hash) => HashLookup::Remapped(current_hash),
+                None => HashLookup::Unresolved(format!(
+                    "beatmap ID {} not in the current osu!.db",
+                    beatmap_id
+                )),
+            },
+        }
+    }
+}
+
+/// Counts of what happened to each hash, and a line for each one that couldn't be remapped.
+#[derive(Default)]
+struct RemapReport {
+    remapped: usize,
+    unchanged: usize,
+    unresolved: Vec<String>,
+}
+
+impl Remap {
+    /// Remap the stale hashes in the collection.db or scores.db in `bytes` and write the result to
+    /// `--output`.
+    pub fn remap(&self, jobs: usize, db_type: DbIndicator, bytes: &[u8]) {
+        if db_type == DbIndicator::OsuDb {
+            println!("Remapping hashes only works on collection.db and scores.db.");
+            return;
+        }
+        if !self.output.can_write() {
+            return;
+        }
+        let old_beatmap_ids = match (&self.old_osu_db_path, &self.mapping_path) {
+            (Some(path), _) => {
+                let old_buffer = read_osu_db(path);
+                let old_osu_db = OsuDb::read_from_bytes(jobs, &old_buffer)
+                    .expect("Failed to parse old osu!.db.");
+                old_osu_db
+                    .beatmaps
+                    .iter()
+                    .map(|beatmap| (beatmap.md5_beatmap_hash.to_string(), beatmap.beatmap_id))
+                    .collect()
+            }
+            (None, Some(path)) => match read_mapping(path) {
+                Ok(mapping) => mapping,
+                Err(msg) => {
+                    println!("{}", msg);
+                    return;
+                }
+            },
+            (None, None) => unreachable!(),
+        };
+        let osu_buffer = read_osu_db(&self.osu_db_path);
+        let osu_db = OsuDb::read_from_bytes(jobs, &osu_buffer).expect("Failed to parse osu!.db.");
+        let remapper = Remapper::new(&osu_db, old_beatmap_ids);
+        let mut report = RemapReport::default();
+        let remapped = match db_type {
+            DbIndicator::CollectionDb => {
+                let mut collection_db =
+                    CollectionDb::read_from_bytes(jobs, bytes).expect("Failed to parse database.");
+                remap_collection_db(&mut collection_db, &remapper, &mut report);
+                collection_db.write_to_bytes()
+            }
+            DbIndicator::ScoresDb => {
+                let mut scores_db =
+                    ScoresDb::read_from_bytes(jobs, bytes).expect("Failed to parse database.");
+                remap_scores_db(&mut scores_db, &remapper, &mut report);
+                scores_db.write_to_bytes()
+            }
+            DbIndicator::OsuDb => unreachable!(),
+        };
+        self.output
+            .write(&remapped)
+            .expect("Failed to write remapped database.");
+        println!(
+            "Remapped {} hashes, {} were already current, {} couldn't be remapped. Wrote {}",
+            report.remapped,
+            report.unchanged,
+            report.unresolved.len(),
+            self.output.path
+        );
+        if report.unresolved.is_empty() {
+            return;
+        }
+        match &self.report_path {
+            Some(path) => {
+                let mut contents = report.unresolved.join("\n");
+                contents.push('\n');
+                // Backups are for the database; the report is regenerated on every run.
+                write_atomically(Path::new(path), contents.as_bytes(), 0)
+                    .expect("Failed to write remap report.");
+                println!("Wrote the hashes that couldn't be remapped to {}", path);
+            }
+            None => {
+                println!("Couldn't remap:");
+                for line in &report.unresolved {
+                    println!("  {}", line);
+                }
+            }
+        }
+    }
+}
+
+fn read_mapping(path: &str) -> Result<HashMap<String, i32>, String> {
+    let contents = fs::read_to_string(path).expect("Failed to read hash mapping file.");
+    let mut mapping = HashMap::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|field| !field.is_empty())
+            .collect::<Vec<_>>();
+        let (hash, beatmap_id) = match fields[..] {
+            [hash, beatmap_id] => (hash, beatmap_id),
+            _ => {
+                return Err(format!(
+                    "Expected a hash and a beatmap ID on line {} of {}: {}",
+                    number + 1,
+                    path,
+                    line
+                ))
+            }
+        };
+        let beatmap_id = beatmap_id.parse::<i32>().map_err(|_| {
+            format!(
+                "Invalid beatmap ID on line {} of {}: {}",
+                number + 1,
+                path,
+                beatmap_id
+            )
+        })?;
+        mapping.insert(hash.to_lowercase(), beatmap_id);
+    }
+    Ok(mapping)
+}
+
+fn remap_collection_db<'a>(
+    collection_db: &mut CollectionDb<'a>,
+    remapper: &Remapper<'a>,
+    report: &mut RemapReport,
+) {
+    for collection in &mut collection_db.collections {
+        let name = collection
+            .collection_name
+            .as_deref()
+            .unwrap_or("")
+            .to_string();
+        let mut hashes = Vec::with_capacity(collection.md5_beatmap_hashes.len());
+        for &hash in &collection.md5_beatmap_hashes {
+            let hash = match remapper.remap(hash) {
+                HashLookup::Current => {
+                    report.unchanged += 1;
+                    hash
+                }
+                HashLookup::Remapped(current_hash) => {
+                    report.remapped += 1;
+                    current_hash
+                }
+                HashLookup::Unresolved(reason) => {
+                    report
+                        .unresolved
+                        .push(format!("collection \"{}\": {} ({})", name, hash, reason));
+                    hash
+                }
+            };
+            // A collection may already have the updated beatmap as well as the old one.
+            if !hashes.contains(&hash) {
+                hashes.push(hash);
+            }
+        }
+        collection.number_of_beatmaps = hashes.len() as i32;
+        collection.md5_beatmap_hashes = hashes;
+    }
+}
+
+/// Remap the hash of each beatmap along with its scores. Scores set on the old version of a beatmap
+/// are moved over to the current version's entry should that already exist.
+fn remap_scores_db<'a>(
+    scores_db: &mut ScoresDb<'a>,
+    remapper: &Remapper<'a>,
+    report: &mut RemapReport,
+) {
+    let mut beatmaps: Vec<ScoresDbBeatmap<'a>> = Vec::with_capacity(scores_db.beatmaps.len());
+    let mut positions = HashMap::new();
+    for mut beatmap in scores_db.beatmaps.drain(..) {
+        let old_hash = beatmap.md5_beatmap_hash;
+        match remapper.remap(old_hash) {
+            HashLookup::Current => report.unchanged += 1,
+            HashLookup::Remapped(current_hash) => {
+                report.remapped += 1;
+                beatmap.md5_beatmap_hash = current_hash;
+                for score in beatmap.scores.iter_mut().flatten() {
+                    if score.md5_beatmap_hash == old_hash {
+                        score.md5_beatmap_hash = current_hash;
+                    }
+                }
+            }
+            HashLookup::Unresolved(reason) => report.unresolved.push(format!(
+                "scores.db: {} with {} scores ({})",
+                old_hash,
+                beatmap.scores.as_ref().map_or(0, Vec::len),
+                reason
+            )),
+        }
+        match positions.get(beatmap.md5_beatmap_hash) {
+            Some(&position) => {
+                let existing: &mut ScoresDbBeatmap = &mut beatmaps[position];
+                let existing_scores = existing.scores.get_or_insert_with(Vec::new);
+                for score in beatmap.scores.into_iter().flatten() {
+                    if !existing_scores
+                        .iter()
+                        .any(|existing| is_same_score(existing, &score))
+                    {
+                        existing_scores.push(score);
+                    }
+                }
+                // The game lists local scores from highest to lowest.
+                existing_scores.sort_by_key(|score| Reverse(score.replay_score));
+                existing.number_of_scores = existing_scores.len() as i32;
+            }
+            None => {
+                positions.insert(beatmap.md5_beatmap_hash, beatmaps.len());
+                beatmaps.push(beatmap);
+            }
+        }
+    }
+    scores_db.number_of_beatmaps = beatmaps.len() as i32;
+    scores_db.beatmaps = beatmaps;
+}
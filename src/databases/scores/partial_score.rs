@@ -1,4 +1,7 @@
-use crate::databases::osu::primitives::GameplayMode;
+use crate::databases::{
+    osu::primitives::{GameplayMode, Mods},
+    scores::score::{Grade, Judgements},
+};
 use crate::load_settings::scores::score_load_settings::ScoreLoadSettings;
 use crate::maybe_deserialize_primitives::*;
 use crate::read_error::ParseFileResult;
@@ -21,11 +24,13 @@ pub struct PartialScore<'a> {
     pub replay_score: Option<i32>,
     pub max_combo: Option<i16>,
     pub perfect_combo: Option<bool>,
-    pub mods_used: Option<i32>,
+    pub mods_used: Option<Mods>,
     pub empty_string: Option<&'a str>,
     pub replay_timestamp: Option<NaiveDate>,
     pub negative_one: Option<i32>,
     pub online_score_id: Option<i64>,
+    pub accuracy: Option<f64>,
+    pub grade: Option<Grade>,
 }
 
 impl<'a> PartialScore<'a> {
@@ -51,7 +56,7 @@ impl<'a> PartialScore<'a> {
         let replay_score = maybe_read_int(settings.replay_score, s, bytes, i)?;
         let max_combo = maybe_read_short(settings.max_combo, s, bytes, i)?;
         let perfect_combo = maybe_read_boolean(settings.perfect_combo, s, bytes, i)?;
-        let mods_used = maybe_read_int(settings.mods_used, s, bytes, i)?;
+        let mods_used = Mods::maybe_read_from_bytes(settings.mods_used, s, bytes, i)?;
         let empty_string =
             maybe_read_str_utf8_nocomp(settings.empty_string, s, bytes, i, "empty string")?;
         let replay_timestamp = maybe_read_datetime(settings.replay_timestamp, s, bytes, i)?;
@@ -60,7 +65,7 @@ impl<'a> PartialScore<'a> {
         if *s {
             Ok(None)
         } else {
-            Ok(Some(PartialScore {
+            let mut score = PartialScore {
                 gameplay_mode,
                 score_version,
                 md5_beatmap_hash,
@@ -80,10 +85,49 @@ impl<'a> PartialScore<'a> {
                 replay_timestamp,
                 negative_one,
                 online_score_id,
-            }))
+                accuracy: None,
+                grade: None,
+            };
+            if score.calculate_grade(settings) {
+                Ok(Some(score))
+            } else {
+                Ok(None)
+            }
         }
     }
 
+    /// The hit counts, if they were all loaded.
+    pub fn judgements(&self) -> Option<Judgements> {
+        Some(Judgements {
+            number_of_300s: self.number_of_300s?,
+            number_of_100s: self.number_of_100s?,
+            number_of_50s: self.number_of_50s?,
+            number_of_gekis: self.number_of_gekis?,
+            number_of_katus: self.number_of_katus?,
+            number_of_misses: self.number_of_misses?,
+        })
+    }
+
+    /// Fill in the accuracy and grade if `settings` asks for them, and return whether they match
+    /// `settings`.
+    pub(crate) fn calculate_grade(&mut self, settings: &ScoreLoadSettings) -> bool {
+        if !settings.is_grading() {
+            return true;
+        }
+        let (judgements, gameplay_mode, mods_used) =
+            match (self.judgements(), self.gameplay_mode, self.mods_used) {
+                (Some(judgements), Some(gameplay_mode), Some(mods_used)) => {
+                    (judgements, gameplay_mode, mods_used)
+                }
+                _ => return true,
+            };
+        let accuracy = judgements.accuracy(gameplay_mode);
+        let grade = judgements.grade(gameplay_mode, mods_used);
+        self.accuracy = Some(accuracy);
+        self.grade = Some(grade);
+        settings.accuracy.matches(&accuracy) && settings.grade.matches(grade)
+    }
+
     pub fn display(&self, show: ScoreMask) {
         maybe_print!(show.gameplay_mode, self.gameplay_mode, "        ");
         maybe_print!(show.score_version, self.score_version, "        ");
@@ -108,5 +152,11 @@ impl<'a> PartialScore<'a> {
         maybe_print!(show.replay_timestamp, self.replay_timestamp, "        ");
         maybe_print!(show.negative_one, self.negative_one, "        ");
         maybe_print!(show.online_score_id, self.online_score_id, "        ");
+        if let (true, Some(accuracy)) = (show.accuracy, self.accuracy) {
+            println!("        accuracy: {:.2}%", accuracy);
+        }
+        if let (true, Some(grade)) = (show.grade, self.grade) {
+            println!("        grade: {}", grade);
+        }
     }
 }
@@ -1,6 +1,8 @@
 use crate::databases::{
-    osu::primitives::GameplayMode,
-    scores::{partial_score::PartialScore, partial_scoresdb_beatmap::PartialScoresDbBeatmap},
+    osu::primitives::{GameplayMode, Mods},
+    scores::{
+        partial_score::PartialScore, partial_scoresdb_beatmap::PartialScoresDbBeatmap, score::Score,
+    },
 };
 use crate::deserialize_primitives::*;
 use crate::load_settings::scores::{
@@ -8,9 +10,7 @@ use crate::load_settings::scores::{
     scoresdb_load_settings::ScoresDbLoadSettings,
 };
 use crate::maybe_deserialize_primitives::*;
-use crate::read_error::{
-    DbFileParseError, ParseErrorKind, ParseErrorKind::PrimitiveError, ParseFileResult,
-};
+use crate::read_error::{DbFileParseError, ParseErrorKind, ParseFileResult};
 use crate::{masks::scores_mask::ScoresDbMask, maybe_print};
 use crossbeam_utils::thread::{self, Scope, ScopedJoinHandle};
 use std::ops::DerefMut;
@@ -187,66 +187,7 @@ fn spawn_partial_scoresdb_beatmap_loader_thread<'scope, 'b: 'scope, 'a: 'b>(
                 let number_of_scores = read_int(bytes, start.deref_mut())?;
                 let section_start = *start;
                 for _ in 0..number_of_scores {
-                    // Skips:
-                    // 1 byte for gameplay_mode
-                    // 4 bytes for score_version
-                    // 34 bytes for MD5 beatmap hash (assumed never to be missing)
-                    *start += 39;
-                    // Assuming 32 characters max length for username, +2 for indicator and ULEB128
-                    let indicator = *bytes.get(*start).ok_or_else(|| {
-                        DbFileParseError::new(
-                            PrimitiveError,
-                            "Failed to read \
-                                                indicator for player name.",
-                        )
-                    })?;
-                    let player_name_len = if indicator == 0x0b {
-                        Ok(*bytes.get(*start + 1).ok_or_else(|| {
-                            DbFileParseError::new(
-                                PrimitiveError,
-                                "Failed to read player name length.",
-                            )
-                        })?)
-                    } else if indicator == 0 {
-                        Ok(0)
-                    } else {
-                        Err(DbFileParseError::new(
-                            PrimitiveError,
-                            "Read invalid indicator for score \
-                                                player name.",
-                        ))
-                    }?;
-                    // Check if greater than or equal to 32.
-                    if player_name_len & 0b10000000 == 0b10000000 {
-                        return Err(DbFileParseError::new(
-                            PrimitiveError,
-                            "Read invalid player name \
-                                                length.",
-                        ));
-                    }
-                    if indicator == 0 {
-                        *start += 1;
-                    } else {
-                        *start += 2;
-                    }
-                    *start += player_name_len as usize + 78;
-                    // Skips:
-                    // 34 bytes for replay MD5 hash
-                    // 2 bytes for number of 300s
-                    // 2 bytes for number of 100s
-                    // 2 bytes for number of 50s
-                    // 2 bytes for number of gekis
-                    // 2 bytes for number of katus
-                    // 2 bytes for number of misses
-                    // 4 bytes for score
-                    // 2 bytes for max combo
-                    // 1 byte for perfect combo
-                    // 4 bytes for mods used
-                    // 1 byte for empty string indicator
-                    // 8 bytes for replay timestamp
-                    // 4 bytes for 0xFFFFFFFF
-                    // 8 bytes for score ID
-                    // Total of 78
+                    Score::skip(bytes, start.deref_mut())?;
                 }
                 (md5_beatmap_hash, number_of_scores, section_start, number)
             };
@@ -257,6 +198,8 @@ fn spawn_partial_scoresdb_beatmap_loader_thread<'scope, 'b: 'scope, 'a: 'b>(
                 let mut tmp = Vec::with_capacity(number_of_scores as usize);
                 let i = &mut start_read;
                 for _ in 0..number_of_scores {
+                    // Every field is read even once the score is filtered out, since the next
+                    // score starts right after this one.
                     let mut skip = false;
                     let s = &mut skip;
                     let gameplay_mode = GameplayMode::maybe_read_from_bytes(
@@ -265,17 +208,14 @@ fn spawn_partial_scoresdb_beatmap_loader_thread<'scope, 'b: 'scope, 'a: 'b>(
                         bytes,
                         i,
                     )?;
-                    continue_if!(*s);
                     let score_version =
                         maybe_read_int(settings.score_load_settings.score_version, s, bytes, i)?;
-                    continue_if!(*s);
                     let md5_beatmap_hash = maybe_read_md5_hash(
                         &settings.score_load_settings.md5_beatmap_hash,
                         s,
                         bytes,
                         i,
                     )?;
-                    continue_if!(*s);
                     let player_name = maybe_read_str_utf8(
                         &settings.score_load_settings.player_name,
                         s,
@@ -289,53 +229,46 @@ fn spawn_partial_scoresdb_beatmap_loader_thread<'scope, 'b: 'scope, 'a: 'b>(
                         bytes,
                         i,
                     )?;
-                    continue_if!(*s);
                     let number_of_300s =
                         maybe_read_short(settings.score_load_settings.number_of_300s, s, bytes, i)?;
-                    continue_if!(*s);
                     let number_of_100s =
                         maybe_read_short(settings.score_load_settings.number_of_100s, s, bytes, i)?;
-                    continue_if!(*s);
                     let number_of_50s =
                         maybe_read_short(settings.score_load_settings.number_of_50s, s, bytes, i)?;
-                    continue_if!(*s);
                     let number_of_gekis = maybe_read_short(
                         settings.score_load_settings.number_of_gekis,
                         s,
                         bytes,
                         i,
                     )?;
-                    continue_if!(*s);
                     let number_of_katus = maybe_read_short(
                         settings.score_load_settings.number_of_katus,
                         s,
                         bytes,
                         i,
                     )?;
-                    continue_if!(*s);
                     let number_of_misses = maybe_read_short(
                         settings.score_load_settings.number_of_misses,
                         s,
                         bytes,
                         i,
                     )?;
-                    continue_if!(*s);
                     let replay_score =
                         maybe_read_int(settings.score_load_settings.replay_score, s, bytes, i)?;
-                    continue_if!(*s);
                     let max_combo =
                         maybe_read_short(settings.score_load_settings.max_combo, s, bytes, i)?;
-                    continue_if!(*s);
                     let perfect_combo = maybe_read_boolean(
                         settings.score_load_settings.perfect_combo,
                         s,
                         bytes,
                         i,
                     )?;
-                    continue_if!(*s);
-                    let mods_used =
-                        maybe_read_int(settings.score_load_settings.mods_used, s, bytes, i)?;
-                    continue_if!(*s);
+                    let mods_used = Mods::maybe_read_from_bytes(
+                        settings.score_load_settings.mods_used,
+                        s,
+                        bytes,
+                        i,
+                    )?;
                     let empty_string = maybe_read_str_utf8_nocomp(
                         settings.score_load_settings.empty_string,
                         s,
@@ -343,25 +276,22 @@ fn spawn_partial_scoresdb_beatmap_loader_thread<'scope, 'b: 'scope, 'a: 'b>(
                         i,
                         "empty string",
                     )?;
-                    continue_if!(*s);
                     let replay_timestamp = maybe_read_datetime(
                         settings.score_load_settings.replay_timestamp,
                         s,
                         bytes,
                         i,
                     )?;
-                    continue_if!(*s);
                     let negative_one = maybe_read_int_nocomp(
                         settings.score_load_settings.negative_one,
                         s,
                         bytes,
                         i,
                     )?;
-                    continue_if!(*s);
                     let online_score_id =
                         maybe_read_long(settings.score_load_settings.online_score_id, s, bytes, i)?;
                     continue_if!(*s);
-                    tmp.push(PartialScore {
+                    let mut score = PartialScore {
                         gameplay_mode,
                         score_version,
                         md5_beatmap_hash,
@@ -381,7 +311,12 @@ fn spawn_partial_scoresdb_beatmap_loader_thread<'scope, 'b: 'scope, 'a: 'b>(
                         replay_timestamp,
                         negative_one,
                         online_score_id,
-                    })
+                        accuracy: None,
+                        grade: None,
+                    };
+                    if score.calculate_grade(&settings.score_load_settings) {
+                        tmp.push(score);
+                    }
                 }
                 Some(tmp)
             };
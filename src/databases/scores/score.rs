@@ -1,9 +1,161 @@
-use crate::databases::osu::primitives::GameplayMode;
+use crate::databases::osu::primitives::{GameplayMode, Mods};
 use crate::deserialize_primitives::*;
 use crate::read_error::ParseFileResult;
-use chrono::NaiveDate;
+use crate::serialize_primitives::*;
+use chrono::NaiveDateTime;
+use std::fmt::{self, Display};
+use std::str::FromStr;
 
-#[derive(Debug, Clone)]
+/// The letter grade osu! gives a score. The silver grades, shown as SSH and SH, are for scores set
+/// with hidden or flashlight.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Grade {
+    SilverSS,
+    SS,
+    SilverS,
+    S,
+    A,
+    B,
+    C,
+    D,
+}
+
+impl Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let grade = match self {
+            Grade::SilverSS => "SSH",
+            Grade::SS => "SS",
+            Grade::SilverS => "SH",
+            Grade::S => "S",
+            Grade::A => "A",
+            Grade::B => "B",
+            Grade::C => "C",
+            Grade::D => "D",
+        };
+        write!(f, "{}", grade)
+    }
+}
+
+impl FromStr for Grade {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_uppercase().as_str() {
+            "SSH" | "XH" => Ok(Grade::SilverSS),
+            "SS" | "X" => Ok(Grade::SS),
+            "SH" => Ok(Grade::SilverS),
+            "S" => Ok(Grade::S),
+            "A" => Ok(Grade::A),
+            "B" => Ok(Grade::B),
+            "C" => Ok(Grade::C),
+            "D" => Ok(Grade::D),
+            _ => Err(format!("Unrecognized grade: {}", s)),
+        }
+    }
+}
+
+/// The hit counts of a score, which its accuracy and grade are calculated from. What each count
+/// means depends on the gameplay mode, as noted on `Score`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Judgements {
+    pub number_of_300s: i16,
+    pub number_of_100s: i16,
+    pub number_of_50s: i16,
+    pub number_of_gekis: i16,
+    pub number_of_katus: i16,
+    pub number_of_misses: i16,
+}
+
+impl Judgements {
+    /// Accuracy as a percentage, the way osu! calculates it for `gameplay_mode`.
+    pub fn accuracy(&self, gameplay_mode: GameplayMode) -> f64 {
+        let n300 = f64::from(self.number_of_300s);
+        let n100 = f64::from(self.number_of_100s);
+        let n50 = f64::from(self.number_of_50s);
+        let gekis = f64::from(self.number_of_gekis);
+        let katus = f64::from(self.number_of_katus);
+        let misses = f64::from(self.number_of_misses);
+        let (points, max_points) = match gameplay_mode {
+            GameplayMode::Standard => (
+                300.0 * n300 + 100.0 * n100 + 50.0 * n50,
+                300.0 * (n300 + n100 + n50 + misses),
+            ),
+            GameplayMode::Taiko => (n300 + 0.5 * n100, n300 + n100 + misses),
+            // Katus are missed droplets.
+            GameplayMode::Ctb => (n300 + n100 + n50, n300 + n100 + n50 + katus + misses),
+            GameplayMode::Mania => (
+                300.0 * (gekis + n300) + 200.0 * katus + 100.0 * n100 + 50.0 * n50,
+                300.0 * (gekis + n300 + katus + n100 + n50 + misses),
+            ),
+        };
+        if max_points > 0.0 {
+            100.0 * points / max_points
+        } else {
+            0.0
+        }
+    }
+
+    /// The grade osu! gives for these hits in `gameplay_mode`. Standard and taiko grade by the
+    /// ratio of 300s and misses, while catch the beat and mania grade by accuracy.
+    pub fn grade(&self, gameplay_mode: GameplayMode, mods: Mods) -> Grade {
+        let accuracy = self.accuracy(gameplay_mode);
+        let grade = match gameplay_mode {
+            GameplayMode::Standard | GameplayMode::Taiko => {
+                let total = i32::from(self.number_of_300s)
+                    + i32::from(self.number_of_100s)
+                    + i32::from(self.number_of_50s)
+                    + i32::from(self.number_of_misses);
+                let ratio = |count: i16| {
+                    if total > 0 {
+                        f64::from(count) / f64::from(total)
+                    } else {
+                        0.0
+                    }
+                };
+                let ratio_300s = ratio(self.number_of_300s);
+                let full_combo = self.number_of_misses == 0;
+                if total > 0 && i32::from(self.number_of_300s) == total {
+                    Grade::SS
+                } else if ratio_300s > 0.9 && ratio(self.number_of_50s) < 0.01 && full_combo {
+                    Grade::S
+                } else if (ratio_300s > 0.8 && full_combo) || ratio_300s > 0.9 {
+                    Grade::A
+                } else if (ratio_300s > 0.7 && full_combo) || ratio_300s > 0.8 {
+                    Grade::B
+                } else if ratio_300s > 0.6 {
+                    Grade::C
+                } else {
+                    Grade::D
+                }
+            }
+            GameplayMode::Ctb => match accuracy {
+                a if a >= 100.0 => Grade::SS,
+                a if a > 98.0 => Grade::S,
+                a if a > 94.0 => Grade::A,
+                a if a > 90.0 => Grade::B,
+                a if a > 85.0 => Grade::C,
+                _ => Grade::D,
+            },
+            GameplayMode::Mania => match accuracy {
+                a if a >= 100.0 => Grade::SS,
+                a if a > 95.0 => Grade::S,
+                a if a > 90.0 => Grade::A,
+                a if a > 80.0 => Grade::B,
+                a if a > 70.0 => Grade::C,
+                _ => Grade::D,
+            },
+        };
+        // Fade in is mania's version of hidden.
+        let silver = mods.intersects(Mods::HIDDEN | Mods::FLASHLIGHT | Mods::FADE_IN);
+        match grade {
+            Grade::SS if silver => Grade::SilverSS,
+            Grade::S if silver => Grade::SilverS,
+            grade => grade,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Score<'a> {
     pub gameplay_mode: GameplayMode,
     pub score_version: i32,
@@ -19,9 +171,9 @@ pub struct Score<'a> {
     pub replay_score: i32,
     pub max_combo: i16,
     pub perfect_combo: bool,
-    pub mods_used: i32,
+    pub mods_used: Mods,
     pub empty_string: Option<&'a str>,
-    pub replay_timestamp: NaiveDate,
+    pub replay_timestamp: NaiveDateTime,
     pub negative_one: i32,
     pub online_score_id: i64,
 }
@@ -42,7 +194,7 @@ impl<'a> Score<'a> {
         let replay_score = read_int(bytes, i)?;
         let max_combo = read_short(bytes, i)?;
         let perfect_combo = read_boolean(bytes, i)?;
-        let mods_used = read_int(bytes, i)?;
+        let mods_used = Mods::read_from_bytes(bytes, i)?;
         let empty_string = read_str_utf8(bytes, i, "empty string")?;
         let replay_timestamp = read_datetime(bytes, i)?;
         let negative_one = read_int(bytes, i)?;
@@ -70,6 +222,65 @@ impl<'a> Score<'a> {
         })
     }
 
+    /// Move `i` past the score starting there without keeping any of it, so that loader threads
+    /// can find where the next beatmap's scores start. The hashes and strings are read to get
+    /// their lengths, since they may be missing.
+    pub fn skip(bytes: &[u8], i: &mut usize) -> ParseFileResult<()> {
+        // Gameplay mode and score version
+        *i += 5;
+        read_md5_hash(bytes, i)?;
+        read_str_utf8(bytes, i, "player name")?;
+        read_md5_hash(bytes, i)?;
+        // Judgements, score, max combo, perfect combo, and mods
+        *i += 23;
+        read_str_utf8(bytes, i, "empty string")?;
+        // Replay timestamp, negative one, and online score ID
+        *i += 20;
+        Ok(())
+    }
+
+    pub fn judgements(&self) -> Judgements {
+        Judgements {
+            number_of_300s: self.number_of_300s,
+            number_of_100s: self.number_of_100s,
+            number_of_50s: self.number_of_50s,
+            number_of_gekis: self.number_of_gekis,
+            number_of_katus: self.number_of_katus,
+            number_of_misses: self.number_of_misses,
+        }
+    }
+
+    /// Accuracy as a percentage.
+    pub fn accuracy(&self) -> f64 {
+        self.judgements().accuracy(self.gameplay_mode)
+    }
+
+    pub fn grade(&self) -> Grade {
+        self.judgements().grade(self.gameplay_mode, self.mods_used)
+    }
+
+    pub fn write_to_bytes(&self, bytes: &mut Vec<u8>) {
+        self.gameplay_mode.write_to_bytes(bytes);
+        write_int(self.score_version, bytes);
+        write_md5_hash(self.md5_beatmap_hash, bytes);
+        write_str_utf8(self.player_name, bytes);
+        write_md5_hash(self.md5_replay_hash, bytes);
+        write_short(self.number_of_300s, bytes);
+        write_short(self.number_of_100s, bytes);
+        write_short(self.number_of_50s, bytes);
+        write_short(self.number_of_gekis, bytes);
+        write_short(self.number_of_katus, bytes);
+        write_short(self.number_of_misses, bytes);
+        write_int(self.replay_score, bytes);
+        write_short(self.max_combo, bytes);
+        write_boolean(self.perfect_combo, bytes);
+        self.mods_used.write_to_bytes(bytes);
+        write_str_utf8(self.empty_string, bytes);
+        write_datetime(self.replay_timestamp, bytes);
+        write_int(self.negative_one, bytes);
+        write_long(self.online_score_id, bytes);
+    }
+
     pub fn display(&self) {
         println!("        gameplay mode: {}", self.gameplay_mode);
         println!("        score version: {}", self.score_version);
@@ -93,6 +304,8 @@ impl<'a> Score<'a> {
         println!("        max combo: {}", self.max_combo);
         println!("        perfect combo: {}", self.perfect_combo);
         println!("        mods used: {}", self.mods_used);
+        println!("        accuracy: {:.2}%", self.accuracy());
+        println!("        grade: {}", self.grade());
         if self.empty_string.is_some() {
             println!(
                 "        empty string: {}",
@@ -1,6 +1,7 @@
 use crate::databases::scores::{score::Score, scoresdb_beatmap::ScoresDbBeatmap};
 use crate::deserialize_primitives::*;
 use crate::read_error::{DbFileParseError, ParseErrorKind, ParseFileResult};
+use crate::serialize_primitives::*;
 use crossbeam_utils::thread::{self, Scope, ScopedJoinHandle};
 use std::ops::DerefMut;
 use std::sync::{Arc, Mutex};
@@ -93,6 +94,18 @@ impl<'a> ScoresDb<'a> {
         })
     }
 
+    /// Write the database to a buffer. The number of beatmaps is taken from `beatmaps` rather than
+    /// `number_of_beatmaps`.
+    pub fn write_to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_int(self.version, &mut bytes);
+        write_int(self.beatmaps.len() as i32, &mut bytes);
+        for beatmap in &self.beatmaps {
+            beatmap.write_to_bytes(&mut bytes);
+        }
+        bytes
+    }
+
     pub fn display(&self) {
         println!("version: {}", self.version);
         println!("number of beatmaps: {}", self.number_of_beatmaps);
@@ -127,66 +140,7 @@ fn spawn_scoresdb_beatmap_loader_thread<'scope, 'b: 'scope, 'a: 'b>(
                 let number_of_scores = read_int(bytes, start.deref_mut())?;
                 let start_from = *start;
                 for _ in 0..number_of_scores {
-                    // Skips:
-                    // 1 byte for gameplay_mode
-                    // 4 bytes for score_version
-                    // 34 bytes for MD5 beatmap hash/1 byte if indicator is 0
-                    *start += 39;
-                    // Assuming 32 characters max length for username, +2 for
-                    // indicator and ULEB128
-                    let indicator = *bytes.get(*start).ok_or_else(|| {
-                        DbFileParseError::new(
-                            ParseErrorKind::PrimitiveError,
-                            "Failed to read \
-                                                indicator for player name.",
-                        )
-                    })?;
-                    let player_name_len = if indicator == 0x0b {
-                        *bytes.get(*start + 1).ok_or_else(|| {
-                            DbFileParseError::new(
-                                ParseErrorKind::PrimitiveError,
-                                "Failed to read player name length.",
-                            )
-                        })?
-                    } else if indicator == 0 {
-                        0
-                    } else {
-                        return Err(DbFileParseError::new(
-                            ParseErrorKind::PrimitiveError,
-                            "Read invalid indicator for score \
-                                                player name.",
-                        ));
-                    };
-                    if player_name_len & 0b10000000 == 0b10000000 {
-                        return Err(DbFileParseError::new(
-                            ParseErrorKind::PrimitiveError,
-                            "Read invalid player name \
-                                                length.",
-                        ));
-                    }
-                    if indicator == 0 {
-                        *start += 1;
-                    } else {
-                        *start += 2;
-                    }
-                    *start += player_name_len as usize + 78;
-                    // Skips:
-                    // 34 bytes for replay MD5 hash
-                    // 2 bytes for number of 300s
-                    // 2 bytes for number of 100s
-                    // 2 bytes for number of 50s
-                    // 2 bytes for number of gekis
-                    // 2 bytes for number of katus
-                    // 2 bytes for number of misses
-                    // 4 bytes for score
-                    // 2 bytes for max combo
-                    // 1 byte for perfect combo
-                    // 4 bytes for mods used
-                    // 1 byte for empty string indicator
-                    // 8 bytes for replay timestamp
-                    // 4 bytes for 0xFFFFFFFF
-                    // 8 bytes for score ID
-                    // Total of 78
+                    Score::skip(bytes, start.deref_mut())?;
                 }
                 (md5_beatmap_hash, number_of_scores, start_from, number)
             };
@@ -211,3 +165,129 @@ fn spawn_scoresdb_beatmap_loader_thread<'scope, 'b: 'scope, 'a: 'b>(
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::databases::osu::primitives::Mods;
+
+    const BEATMAP_HASHES: [&str; 3] = [
+        "0123456789abcdef0123456789abcdef",
+        "fedcba9876543210fedcba9876543210",
+        "00000000000000000000000000000000",
+    ];
+
+    fn write_score(
+        beatmap_hash: &str,
+        replay_hash: &str,
+        mods: i32,
+        empty_string: Option<&str>,
+        negative_one: i32,
+        bytes: &mut Vec<u8>,
+    ) {
+        write_byte(1, bytes); // taiko
+        write_int(20211231, bytes);
+        write_md5_hash(beatmap_hash, bytes);
+        write_str_utf8(Some("Player"), bytes);
+        write_md5_hash(replay_hash, bytes);
+        for &judgement in &[500, 20, 0, 100, 5, 3] {
+            write_short(judgement, bytes);
+        }
+        write_int(987_654, bytes);
+        write_short(700, bytes);
+        write_boolean(false, bytes);
+        write_int(mods, bytes);
+        write_str_utf8(empty_string, bytes);
+        write_long(637_000_000_123_456_789, bytes);
+        write_int(negative_one, bytes);
+        write_long(3_000_000_000, bytes);
+    }
+
+    /// A scores.db whose beatmaps have no scores, one score, and three scores.
+    fn scores_db() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_int(20211231, &mut bytes);
+        write_int(3, &mut bytes);
+        write_md5_hash(BEATMAP_HASHES[0], &mut bytes);
+        write_int(0, &mut bytes);
+        write_md5_hash(BEATMAP_HASHES[1], &mut bytes);
+        write_int(1, &mut bytes);
+        write_score(
+            BEATMAP_HASHES[1],
+            "11111111111111111111111111111111",
+            Mods::NONE.0,
+            None,
+            -1,
+            &mut bytes,
+        );
+        write_md5_hash(BEATMAP_HASHES[2], &mut bytes);
+        write_int(3, &mut bytes);
+        write_score(
+            BEATMAP_HASHES[2],
+            "22222222222222222222222222222222",
+            (Mods::HIDDEN | Mods::NIGHTCORE | Mods::DOUBLE_TIME).0,
+            Some(""),
+            -1,
+            &mut bytes,
+        );
+        // Neither field is known to ever differ from this, but should they, they're kept as is.
+        write_score(
+            BEATMAP_HASHES[2],
+            "33333333333333333333333333333333",
+            (Mods::HARD_ROCK | Mods::PERFECT | Mods::SUDDEN_DEATH).0,
+            Some("not empty"),
+            7,
+            &mut bytes,
+        );
+        // Mods with bits that don't stand for any mod.
+        write_score(BEATMAP_HASHES[2], "", i32::MIN | 1, None, -1, &mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn scores_db_round_trips() {
+        let bytes = scores_db();
+        for &jobs in &[1, 2] {
+            let scores_db = ScoresDb::read_from_bytes(jobs, &bytes).unwrap();
+            let counts = scores_db
+                .beatmaps
+                .iter()
+                .map(|beatmap| beatmap.number_of_scores)
+                .collect::<Vec<_>>();
+            assert_eq!(counts, [0, 1, 3]);
+            let scores = scores_db.beatmaps[2].scores.as_ref().unwrap();
+            assert_eq!(scores[0].empty_string, Some(""));
+            assert_eq!(scores[1].empty_string, Some("not empty"));
+            assert_eq!(scores[1].negative_one, 7);
+            assert_eq!(
+                scores[0].mods_used,
+                Mods::HIDDEN | Mods::NIGHTCORE | Mods::DOUBLE_TIME
+            );
+            assert_eq!(scores[2].mods_used, Mods(i32::MIN | 1));
+            assert_eq!(scores_db.write_to_bytes(), bytes, "{} jobs", jobs);
+        }
+    }
+
+    #[test]
+    fn score_counts_are_recomputed_when_written() {
+        let bytes = scores_db();
+        let mut scores_db = ScoresDb::read_from_bytes(1, &bytes).unwrap();
+        let moved = scores_db.beatmaps[2]
+            .scores
+            .as_mut()
+            .unwrap()
+            .pop()
+            .unwrap();
+        scores_db.beatmaps[0].scores = Some(vec![moved]);
+        scores_db.beatmaps.remove(1);
+        let written = scores_db.write_to_bytes();
+        let read_back = ScoresDb::read_from_bytes(1, &written).unwrap();
+        assert_eq!(read_back.number_of_beatmaps, 2);
+        let counts = read_back
+            .beatmaps
+            .iter()
+            .map(|beatmap| beatmap.number_of_scores)
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [1, 2]);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::databases::scores::score::Score;
 use crate::deserialize_primitives::*;
 use crate::read_error::ParseFileResult;
+use crate::serialize_primitives::*;
 
 #[derive(Debug, Clone)]
 pub struct ScoresDbBeatmap<'a> {
@@ -29,6 +30,17 @@ impl<'a> ScoresDbBeatmap<'a> {
         })
     }
 
+    /// Write the beatmap and its scores to a buffer. The number of scores is taken from `scores`
+    /// rather than `number_of_scores`.
+    pub fn write_to_bytes(&self, bytes: &mut Vec<u8>) {
+        let scores = self.scores.as_deref().unwrap_or(&[]);
+        write_md5_hash(self.md5_beatmap_hash, bytes);
+        write_int(scores.len() as i32, bytes);
+        for score in scores {
+            score.write_to_bytes(bytes);
+        }
+    }
+
     pub fn display(&self) {
         println!("    md5 beatmap hash: {}", self.md5_beatmap_hash);
         println!("    number of scores: {}", self.number_of_scores);
@@ -0,0 +1,217 @@
+use crate::databases::{
+    collection::{collection::Collection, collectiondb::CollectionDb},
+    collection_command::{find_collection, read_osu_db},
+    osu::osudb::OsuDb,
+};
+use crate::load_settings::osu::beatmap_load_settings::BeatmapLoadSettings;
+use crate::output::write_atomically;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs;
+use std::iter;
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+// Collections whose beatmaps are whatever in osu!.db matches a query. The queries are stored next
+// to collection.db in `collection.db.smart`, one collection per line: the collection name followed
+// by the query's arguments, all separated by tabs.
+#[derive(StructOpt)]
+pub struct Smart {
+    #[structopt(value_name = "NAME")]
+    name: Option<String>,
+    #[structopt(
+        long = "refresh",
+        help = "Rebuild smart collections from their stored queries instead of saving a new one. \
+                Without NAME, every smart collection is rebuilt."
+    )]
+    refresh: bool,
+    #[structopt(
+        name = "osu!.db path",
+        long = "osu-db",
+        value_name = "PATH",
+        help = "osu!.db to look up beatmaps matching the query in"
+    )]
+    osu_db_path: String,
+    #[structopt(flatten)]
+    query: BeatmapLoadSettings,
+}
+
+struct SmartQuery {
+    name: String,
+    args: Vec<String>,
+}
+
+impl SmartQuery {
+    fn settings(&self) -> Result<BeatmapLoadSettings, String> {
+        BeatmapLoadSettings::from_iter_safe(
+            iter::once("smart").chain(self.args.iter().map(|arg| arg.as_str())),
+        )
+        .map_err(|e| e.message)
+    }
+}
+
+impl Smart {
+    /// Save the query as a smart collection and build it, or rebuild stored ones with `--refresh`,
+    /// then save collection.db back to `db_path`.
+    pub fn run(&self, jobs: usize, db_path: &str, bytes: &[u8], backups: usize) {
+        let queries_path = smart_queries_path(db_path);
+        let mut queries = match read_smart_queries(&queries_path) {
+            Ok(queries) => queries,
+            Err(msg) => {
+                println!("{}", msg);
+                return;
+            }
+        };
+        let targets = if self.refresh {
+            if !self.query.ignore_all() {
+                println!("--refresh rebuilds from the stored queries and can't take a new one.");
+                return;
+            }
+            match &self.name {
+                Some(name) if !queries.iter().any(|query| &query.name == name) => {
+                    println!("No smart collection named \"{}\".", name);
+                    return;
+                }
+                Some(name) => vec![name.clone()],
+                None => queries.iter().map(|query| query.name.clone()).collect(),
+            }
+        } else {
+            let name = match &self.name {
+                Some(name) => name,
+                None => {
+                    println!("A smart collection needs a name unless using --refresh.");
+                    return;
+                }
+            };
+            if self.query.ignore_all() {
+                println!("No query given for smart collection \"{}\".", name);
+                return;
+            }
+            let args = self.query.to_args();
+            if iter::once(name)
+                .chain(&args)
+                .any(|s| s.contains(&['\t', '\n'][..]))
+            {
+                println!("Smart collection names and queries can't contain tabs or newlines.");
+                return;
+            }
+            match queries.iter_mut().find(|query| &query.name == name) {
+                Some(query) => query.args = args,
+                None => queries.push(SmartQuery {
+                    name: name.clone(),
+                    args,
+                }),
+            }
+            vec![name.clone()]
+        };
+        if targets.is_empty() {
+            println!("No smart collections to refresh.");
+            return;
+        }
+        let osu_buffer = read_osu_db(&self.osu_db_path);
+        let osu_db = OsuDb::read_from_bytes(jobs, &osu_buffer).expect("Failed to parse osu!.db.");
+        let mut collection_db =
+            CollectionDb::read_from_bytes(jobs, bytes).expect("Failed to parse database.");
+        for query in queries.iter().filter(|query| targets.contains(&query.name)) {
+            let settings = match query.settings() {
+                Ok(settings) => settings,
+                Err(msg) => {
+                    println!(
+                        "Failed to parse the stored query of smart collection \"{}\":\n{}",
+                        query.name, msg
+                    );
+                    return;
+                }
+            };
+            let hashes = osu_db
+                .beatmaps
+                .iter()
+                .filter(|beatmap| settings.matches(beatmap))
+                .map(|beatmap| beatmap.md5_beatmap_hash)
+                .collect::<Vec<_>>();
+            let index = find_collection(&collection_db, &query.name).unwrap_or_else(|| {
+                collection_db.collections.push(Collection {
+                    collection_name: Some(Cow::Owned(query.name.clone())),
+                    number_of_beatmaps: 0,
+                    md5_beatmap_hashes: Vec::new(),
+                });
+                collection_db.collections.len() - 1
+            });
+            let collection = &mut collection_db.collections[index];
+            let old_hashes = collection
+                .md5_beatmap_hashes
+                .iter()
+                .copied()
+                .collect::<HashSet<_>>();
+            let new_hashes = hashes.iter().copied().collect::<HashSet<_>>();
+            let added = hashes
+                .iter()
+                .filter(|hash| !old_hashes.contains(*hash))
+                .count();
+            let removed = collection
+                .md5_beatmap_hashes
+                .iter()
+                .filter(|hash| !new_hashes.contains(*hash))
+                .count();
+            collection.md5_beatmap_hashes = hashes;
+            collection.number_of_beatmaps = collection.md5_beatmap_hashes.len() as i32;
+            println!(
+                "Smart collection \"{}\": {} beatmaps ({} added, {} removed).",
+                query.name, collection.number_of_beatmaps, added, removed
+            );
+        }
+        collection_db.number_of_collections = collection_db.collections.len() as i32;
+        write_atomically(Path::new(db_path), &collection_db.write_to_bytes(), backups)
+            .expect("Failed to write collection database.");
+        if !self.refresh {
+            write_atomically(
+                &queries_path,
+                write_smart_queries(&queries).as_bytes(),
+                backups,
+            )
+            .expect("Failed to write smart collection queries.");
+        }
+    }
+}
+
+fn smart_queries_path(db_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.smart", db_path))
+}
+
+fn read_smart_queries(path: &Path) -> Result<Vec<SmartQuery>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = fs::read_to_string(path).expect("Failed to read smart collection queries.");
+    contents
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let mut fields = line.split('\t');
+            match fields.next() {
+                Some(name) if !name.is_empty() => Ok(SmartQuery {
+                    name: name.to_string(),
+                    args: fields.map(String::from).collect(),
+                }),
+                _ => Err(format!(
+                    "Smart collection without a name in {}: {}",
+                    path.display(),
+                    line
+                )),
+            }
+        })
+        .collect()
+}
+
+fn write_smart_queries(queries: &[SmartQuery]) -> String {
+    let mut contents = String::new();
+    for query in queries {
+        contents.push_str(&query.name);
+        for arg in &query.args {
+            contents.push('\t');
+            contents.push_str(arg);
+        }
+        contents.push('\n');
+    }
+    contents
+}
@@ -1,8 +1,10 @@
 use std::mem::size_of;
 use std::str;
-use std::time::Duration;
 
-use chrono::{naive::NaiveDate, Duration as ChronoDuration};
+use chrono::{
+    naive::{NaiveDate, NaiveDateTime},
+    Duration as ChronoDuration,
+};
 
 use crate::read_error::{DbFileParseError, ParseErrorKind::*, ParseFileResult};
 // Primitive types we need to read from databases:
@@ -88,50 +90,30 @@ pub fn read_long(bytes: &[u8], i: &mut usize) -> ParseFileResult<i64> {
 
 #[inline]
 pub fn read_uleb128(bytes: &[u8], i: &mut usize) -> ParseFileResult<usize> {
+    let bits = size_of::<usize>() * 8;
     let mut out = 0;
-    let mut found_end = false;
     let mut shift = 0;
-    while shift < size_of::<usize>() * 8 {
+    loop {
         let b = *bytes.get(*i).ok_or_else(|| primitive!(ULEB128_ERR))?;
         *i += 1;
-        // Handle case when there's less than eight bits left in the usize
-        if shift + 8 >= size_of::<usize>() * 8 {
-            // If the last byte has a value that fits within the remaining number of bits, add it
-            // to our total and break the loop
-            if 0b11111111 >> (size_of::<usize>() * 8 - shift) | b
-                < (0b10000000 >> size_of::<usize>() * 8 - shift - 1)
-            {
-                out += (b as usize) << shift;
-                found_end = true;
-                break;
-            } else {
-                let err_msg = format!(
-                    "While the ULEB128 integer format supports integers \
-                     of arbitrary lengths, this program will only handle ULEB128 integers \
-                     representing integers up to and including {} bits in length.",
-                    size_of::<usize>() * 8
-                );
-                return Err(DbFileParseError::new(PrimitiveError, err_msg.as_str()));
-            }
+        let value = (b & 0b01111111) as usize;
+        // The last byte may have fewer than seven bits left in the usize to fill, in which case
+        // the bits that don't fit have to be zero
+        if shift >= bits || (bits - shift < 7 && value >> (bits - shift) != 0) {
+            let err_msg = format!(
+                "While the ULEB128 integer format supports integers of arbitrary lengths, this \
+                program will only handle ULEB128 integers representing integers up to and \
+                including {} bits in length.",
+                bits
+            );
+            return Err(DbFileParseError::new(PrimitiveError, err_msg.as_str()));
         }
-        out += (b as usize & 0b01111111) << shift;
+        out |= value << shift;
         if b & 0b10000000 == 0 {
-            found_end = true;
-            break;
+            return Ok(out);
         }
         shift += 7;
     }
-    if found_end {
-        Ok(out)
-    } else {
-        let err_msg = format!(
-            "While the ULEB128 integer format supports integers of arbitrary lengths, this program \
-            will only handle ULEB128 integers representing integers up to and including {} bits in \
-            length.",
-            size_of::<usize>() * 8
-        );
-        Err(DbFileParseError::new(PrimitiveError, err_msg.as_str()))
-    }
 }
 
 #[inline]
@@ -193,19 +175,28 @@ pub fn read_boolean(bytes: &[u8], i: &mut usize) -> ParseFileResult<bool> {
     Ok(read_byte(bytes, i).map_err(|_| primitive!(BOOLEAN_ERR))? != 0)
 }
 
+/// .NET `DateTime`s are stored as a number of ticks (100 nanosecond intervals) since midnight of
+/// 0001-01-01.
+#[inline]
+pub fn dotnet_epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(1, 1, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .unwrap()
+}
+
+/// Converts a tick count into a `NaiveDateTime` without losing any precision, so that it can be
+/// turned back into the exact same tick count when written.
+#[inline]
+pub fn datetime_from_ticks(ticks: i64) -> NaiveDateTime {
+    dotnet_epoch()
+        + ChronoDuration::seconds(ticks.div_euclid(10_000_000))
+        + ChronoDuration::nanoseconds(ticks.rem_euclid(10_000_000) * 100)
+}
+
 #[inline]
-pub fn read_datetime(bytes: &[u8], i: &mut usize) -> ParseFileResult<NaiveDate> {
+pub fn read_datetime(bytes: &[u8], i: &mut usize) -> ParseFileResult<NaiveDateTime> {
     let ticks = read_long(bytes, i).map_err(|_| primitive!(DATETIME_ERR))?;
-    let duration_since_epoch = Duration::from_secs(ticks as u64 / 10_000_000);
-    let chrono_duration = ChronoDuration::from_std(duration_since_epoch).map_err(|e| {
-        let msg = format!(
-            "Failed to convert std::time::Duration to chrono::Duration\n\
-             {}",
-            e
-        );
-        DbFileParseError::new(PrimitiveError, msg)
-    })?;
-    Ok(NaiveDate::from_ymd(1970, 1, 1) + chrono_duration)
+    Ok(datetime_from_ticks(ticks))
 }
 
 #[inline]
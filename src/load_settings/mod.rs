@@ -2,6 +2,7 @@ pub mod collection;
 pub mod osu;
 pub mod scores;
 
+use crate::databases::osu::primitives::Mods;
 use crate::load_settings::{
     collection::collectiondb_load_settings::CollectionDbLoadSettings,
     osu::osudb_load_settings::OsuDbLoadSettings,
@@ -76,6 +77,21 @@ impl<T: Copy + Clone + PartialEq> EqualCopy<T> {
             *self = EqualCopy::Load;
         }
     }
+
+    /// Like `compare`, but treats `Ignore` as a match, for filtering already loaded values.
+    pub fn matches(&self, other: T) -> bool {
+        self.is_ignore() || self.compare(other)
+    }
+}
+
+impl<T: Copy + Clone + Display + PartialEq> EqualCopy<T> {
+    /// The value to pass on the command line to get this back, if it filters on anything.
+    pub fn to_arg(self) -> Option<String> {
+        match self {
+            EqualCopy::Eq(eq) => Some(eq.to_string()),
+            _ => None,
+        }
+    }
 }
 
 impl<T: Clone + Copy + Display + PartialEq> Display for EqualCopy<T> {
@@ -151,6 +167,26 @@ impl EqualClone<String> {
             EqualClone::Load => true,
         }
     }
+
+    /// Like `compare_str`, but treats `Ignore` as a match, for filtering already loaded values. A
+    /// missing string only matches if no particular value is asked for.
+    pub fn matches_str(&self, other: Option<&str>) -> bool {
+        match (self, other) {
+            (EqualClone::Eq(_), None) => false,
+            (_, None) => true,
+            (_, Some(other)) => self.is_ignore() || self.compare_str(other),
+        }
+    }
+}
+
+impl<T: Clone + Display + PartialEq> EqualClone<T> {
+    /// The value to pass on the command line to get this back, if it filters on anything.
+    pub fn to_arg(&self) -> Option<String> {
+        match self {
+            EqualClone::Eq(eq) => Some(eq.to_string()),
+            _ => None,
+        }
+    }
 }
 
 impl<T: Clone + Display + PartialEq> Display for EqualClone<T> {
@@ -221,6 +257,11 @@ impl<T: Copy + Clone + PartialEq + PartialOrd> Relational<T> {
             *self = Relational::Load;
         }
     }
+
+    /// Like `compare`, but treats `Ignore` as a match, for filtering already loaded values.
+    pub fn matches(&self, other: &T) -> bool {
+        self.is_ignore() || self.compare(other)
+    }
 }
 
 impl<T: Copy + Clone + FromStr + PartialEq + PartialOrd> FromStr for Relational<T>
@@ -232,55 +273,62 @@ where
     fn from_str(s: &str) -> Result<Self, String> {
         if s == "" || s == "ignore" {
             Ok(Relational::Ignore)
-        } else {
-            // If it's just "4" or "9.2" or something. Not a range.
-            if is_number(s) {
-                Ok(Relational::Eq(s.parse::<T>().map_err(|e| {
-                    format!("Invalid value: {}\nParse error: {:?}", s, e)
-                })?))
-            } else if is_valid_range(s) {
-                let (first, middle) = s.split_at(1);
-                let (middle, last) = middle.split_at(middle.len() - 1);
-                let mut spliterator = middle.split("..");
-                let start_str = spliterator
-                    .next()
-                    .ok_or_else(|| "Missing start of range.".to_string())?;
-                let end_str = spliterator
-                    .next()
-                    .ok_or_else(|| "Missing end of range.".to_string())?;
-                if start_str == "" && end_str == "" {
-                    return Err("At least one of the range bounds must be defined.".into());
-                }
-                let start = start_str
-                    .parse::<T>()
-                    .map_err(|e| format!("Failed to parse start of range.\n{:?}", e))?;
-                let end = end_str
-                    .parse::<T>()
-                    .map_err(|e| format!("Failed to parse end of range.\n{:?}", e))?;
-                Ok(if start_str == "" {
-                    match (first, last) {
-                        ("(", ")") | ("[", ")") => Relational::Lt(end),
-                        ("(", "]") | ("[", "]") => Relational::LtE(end),
-                        _ => unreachable!(),
-                    }
-                } else if end_str == "" {
-                    match (first, last) {
-                        ("(", ")") | ("(", "]") => Relational::Gt(end),
-                        ("[", ")") | ("[", "]") => Relational::GtE(end),
-                        _ => unreachable!(),
-                    }
+        } else if is_valid_range(s) {
+            let (first, middle) = s.split_at(1);
+            let (middle, last) = middle.split_at(middle.len() - 1);
+            let mut spliterator = middle.splitn(2, "..");
+            let start_str = spliterator
+                .next()
+                .ok_or_else(|| "Missing start of range.".to_string())?;
+            let end_str = spliterator
+                .next()
+                .ok_or_else(|| "Missing end of range.".to_string())?;
+            // Either bound may be left out, but not both.
+            let parse_bound = |bound: &str, which: &str| -> Result<Option<T>, String> {
+                if bound.is_empty() {
+                    Ok(None)
                 } else {
-                    match (first, last) {
-                        ("(", ")") => Relational::InEE(start, end),
-                        ("(", "]") => Relational::InEI(start, end),
-                        ("[", ")") => Relational::InIE(start, end),
-                        ("[", "]") => Relational::InII(start, end),
-                        _ => unreachable!(),
-                    }
-                })
-            } else {
-                Err("Input not recognized as value or range.".into())
-            }
+                    bound
+                        .parse::<T>()
+                        .map(Some)
+                        .map_err(|e| format!("Failed to parse {} of range.\n{:?}", which, e))
+                }
+            };
+            let start = parse_bound(start_str, "start")?;
+            let end = parse_bound(end_str, "end")?;
+            Ok(match (start, end) {
+                (None, Some(end)) => match last {
+                    ")" => Relational::Lt(end),
+                    _ => Relational::LtE(end),
+                },
+                (Some(start), None) => match first {
+                    "(" => Relational::Gt(start),
+                    _ => Relational::GtE(start),
+                },
+                (Some(start), Some(end)) => match (first, last) {
+                    ("(", ")") => Relational::InEE(start, end),
+                    ("(", "]") => Relational::InEI(start, end),
+                    ("[", ")") => Relational::InIE(start, end),
+                    ("[", "]") => Relational::InII(start, end),
+                    _ => unreachable!(),
+                },
+                (None, None) => unreachable!(),
+            })
+        } else {
+            Ok(Relational::Eq(s.parse::<T>().map_err(|e| {
+                format!("Invalid value: {}\nParse error: {:?}", s, e)
+            })?))
+        }
+    }
+}
+
+impl<T: Copy + Clone + Display + PartialEq + PartialOrd> Relational<T> {
+    /// The value to pass on the command line to get this back, if it filters on anything.
+    pub fn to_arg(self) -> Option<String> {
+        match self {
+            Relational::Eq(eq) => Some(eq.to_string()),
+            Relational::Ignore | Relational::Load => None,
+            _ => Some(self.to_string()),
         }
     }
 }
@@ -303,43 +351,142 @@ impl<T: Clone + Copy + Display + PartialEq + PartialOrd> Display for Relational<
     }
 }
 
-pub(crate) fn is_number(s: &str) -> bool {
-    let mut period_count = 0;
-    for c in s.chars() {
-        if c == '.' {
-            period_count += 1;
-        } else if !(c.is_numeric() || c.is_ascii_hexdigit()) {
-            return false;
-        }
-        if period_count > 1 {
-            return false;
-        }
+// Queries on a score's mods. Each one that's given has to match, so they can be combined, e.g.
+// `--mods-include HD --mods-exclude EZ` for scores with hidden but without easy.
+#[derive(Copy, Clone, Default, StructOpt)]
+pub struct ModsFilter {
+    #[structopt(
+        name = "mods include",
+        long = "mods-include",
+        value_name = "MODS",
+        parse(try_from_str),
+        help = "Has all of these mods, e.g. HDHR"
+    )]
+    pub include: Option<Mods>,
+    #[structopt(
+        name = "mods any",
+        long = "mods-any",
+        value_name = "MODS",
+        parse(try_from_str),
+        help = "Has at least one of these mods"
+    )]
+    pub any: Option<Mods>,
+    #[structopt(
+        name = "mods exclude",
+        long = "mods-exclude",
+        value_name = "MODS",
+        parse(try_from_str),
+        help = "Has none of these mods"
+    )]
+    pub exclude: Option<Mods>,
+    #[structopt(
+        name = "mods exact",
+        long = "mods-exact",
+        value_name = "MODS",
+        parse(try_from_str),
+        help = "Has exactly these mods, or none for NM"
+    )]
+    pub exact: Option<Mods>,
+    #[structopt(skip)]
+    pub load: bool,
+}
+
+impl ModsFilter {
+    pub fn is_ignore(&self) -> bool {
+        !self.load
+            && self.include.is_none()
+            && self.any.is_none()
+            && self.exclude.is_none()
+            && self.exact.is_none()
+    }
+
+    pub fn apply_mask(&mut self, mask: bool) {
+        self.load |= mask;
+    }
+
+    /// Whether `mods` passes every query. With no queries, everything matches.
+    // `Option::is_none_or` needs Rust 1.82.
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn matches(&self, mods: Mods) -> bool {
+        self.include.map_or(true, |include| mods.contains(include))
+            && self.any.map_or(true, |any| mods.intersects(any))
+            && self
+                .exclude
+                .map_or(true, |exclude| !mods.intersects(exclude))
+            && self.exact.map_or(true, |exact| mods == exact)
     }
-    true
 }
 
+/// Check whether `s` looks like a range, such as `(8..10]` or `[9..)`. The bounds themselves are
+/// left to be parsed as whatever type the range is over.
 pub(crate) fn is_valid_range(s: &str) -> bool {
-    if (s.starts_with('(') || s.starts_with('[')) && (s.ends_with(')') || s.ends_with(']')) {
-        let (_, middle) = s.split_at(1);
-        let (middle, _) = middle.split_at(middle.len() - 1);
-        let mut spliterator = middle.split("..");
-        let start = if let Some(s) = spliterator.next() {
-            s
-        } else {
-            return false;
-        };
-        let end = if let Some(s) = spliterator.next() {
-            s
-        } else {
-            return false;
-        };
-        match (start == "", end == "") {
-            (true, true) => false,
-            (true, false) => is_number(start),
-            (false, true) => is_number(end),
-            (false, false) => is_number(start) && is_number(end),
-        }
+    if s.len() >= 4
+        && (s.starts_with('(') || s.starts_with('['))
+        && (s.ends_with(')') || s.ends_with(']'))
+    {
+        let middle = &s[1..s.len() - 1];
+        middle.contains("..") && middle != ".."
     } else {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<String, String> {
+        s.parse::<Relational<i32>>()
+            .map(|relational| relational.to_string())
+    }
+
+    #[test]
+    fn relational_parses_closed_ranges() {
+        assert_eq!(parse("(1..5)"), Ok("(1..5)".to_string()));
+        assert_eq!(parse("(1..5]"), Ok("(1..5]".to_string()));
+        assert_eq!(parse("[1..5)"), Ok("[1..5)".to_string()));
+        assert_eq!(parse("[-5..-1]"), Ok("[-5..-1]".to_string()));
+    }
+
+    #[test]
+    fn relational_parses_half_open_ranges() {
+        assert_eq!(parse("(..5)"), Ok("(..5)".to_string()));
+        assert_eq!(parse("[..5]"), Ok("(..5]".to_string()));
+        assert_eq!(parse("(1..)"), Ok("(1..)".to_string()));
+        assert_eq!(parse("[1..)"), Ok("[1..)".to_string()));
+    }
+
+    #[test]
+    fn relational_parses_plain_values() {
+        assert_eq!(parse("5"), Ok("=5".to_string()));
+        assert_eq!(parse("-5"), Ok("=-5".to_string()));
+        assert_eq!(parse(""), Ok("ignore".to_string()));
+        assert_eq!(parse("ignore"), Ok("ignore".to_string()));
+    }
+
+    #[test]
+    fn relational_rejects_invalid_input() {
+        // Ranges need brackets, and only take `..` between the bounds.
+        assert!(parse("1..5").is_err());
+        assert!(parse("1..=5").is_err());
+        assert!(parse("..5").is_err());
+        assert!(parse("1..").is_err());
+        assert!(parse("[1..=5]").is_err());
+        assert!(parse("(..)").is_err());
+        assert!(parse("[a..5]").is_err());
+        assert!(parse("[1..b]").is_err());
+        assert!(parse("five").is_err());
+    }
+
+    #[test]
+    fn relational_round_trips_through_to_arg() {
+        for s in &[
+            "(1..5)", "(1..5]", "[1..5)", "[1..5]", "(..5)", "(..5]", "(1..)", "[1..)",
+        ] {
+            let relational = s.parse::<Relational<i32>>().unwrap();
+            assert_eq!(relational.to_arg().as_deref(), Some(*s));
+        }
+        let eq = "5".parse::<Relational<i32>>().unwrap();
+        assert_eq!(eq.to_arg().as_deref(), Some("5"));
+    }
+}
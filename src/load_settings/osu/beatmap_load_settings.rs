@@ -1,4 +1,7 @@
-use crate::databases::osu::primitives::{ByteSingle, GameplayMode, RankedStatus};
+use crate::databases::osu::{
+    beatmap::Beatmap,
+    primitives::{ByteSingle, GameplayMode, RankedStatus},
+};
 use crate::load_settings::{EqualClone, EqualCopy, Relational};
 use crate::masks::osu_mask::BeatmapMask;
 use chrono::naive::NaiveDate;
@@ -541,6 +544,169 @@ impl BeatmapLoadSettings {
             && self.mania_scroll_speed.is_ignore()
     }
 
+    /// Check whether a fully loaded beatmap passes every filter. The star ratings, timing points,
+    /// and unknown short can't be filtered on, so they're not checked.
+    pub fn matches(&self, beatmap: &Beatmap) -> bool {
+        beatmap
+            .entry_size
+            .map_or(true, |entry_size| self.entry_size.matches(&entry_size))
+            && self.artist_name.matches_str(beatmap.artist_name)
+            && self
+                .artist_name_unicode
+                .matches_str(beatmap.artist_name_unicode)
+            && self.song_title.matches_str(beatmap.song_title)
+            && self
+                .song_title_unicode
+                .matches_str(beatmap.song_title_unicode)
+            && self.creator_name.matches_str(beatmap.creator_name)
+            && self.difficulty.matches_str(beatmap.difficulty)
+            && self.audio_file_name.matches_str(beatmap.audio_file_name)
+            && self
+                .md5_beatmap_hash
+                .matches_str(Some(beatmap.md5_beatmap_hash))
+            && self.dotosu_file_name.matches_str(beatmap.dotosu_file_name)
+            && self.ranked_status.matches(beatmap.ranked_status)
+            && self
+                .number_of_hitcircles
+                .matches(&beatmap.number_of_hitcircles)
+            && self.number_of_sliders.matches(&beatmap.number_of_sliders)
+            && self.number_of_spinners.matches(&beatmap.number_of_spinners)
+            && self
+                .last_modification_time
+                .matches(&beatmap.last_modification_time.date())
+            && self.approach_rate.matches(&beatmap.approach_rate)
+            && self.circle_size.matches(&beatmap.circle_size)
+            && self.hp_drain.matches(&beatmap.hp_drain)
+            && self.overall_difficulty.matches(&beatmap.overall_difficulty)
+            && self.slider_velocity.matches(&beatmap.slider_velocity)
+            && self.drain_time.matches(&beatmap.drain_time)
+            && self.total_time.matches(&beatmap.total_time)
+            && self
+                .preview_offset_from_start_ms
+                .matches(&beatmap.preview_offset_from_start_ms)
+            && self
+                .num_timing_points
+                .matches(&(beatmap.timing_points.len() as i32))
+            && self.beatmap_id.matches(&beatmap.beatmap_id)
+            && self.beatmap_set_id.matches(&beatmap.beatmap_set_id)
+            && self.thread_id.matches(&beatmap.thread_id)
+            && self.standard_grade.matches(&beatmap.standard_grade)
+            && self.taiko_grade.matches(&beatmap.taiko_grade)
+            && self.ctb_grade.matches(&beatmap.ctb_grade)
+            && self.mania_grade.matches(&beatmap.mania_grade)
+            && self.local_offset.matches(&beatmap.local_offset)
+            && self.stack_leniency.matches(&beatmap.stack_leniency)
+            && self.gameplay_mode.matches(beatmap.gameplay_mode)
+            && self.song_source.matches_str(beatmap.song_source)
+            && self.song_tags.matches_str(beatmap.song_tags)
+            && self.online_offset.matches(&beatmap.online_offset)
+            && self
+                .font_used_for_song_title
+                .matches_str(beatmap.font_used_for_song_title)
+            && self.unplayed.matches(beatmap.unplayed)
+            && self.last_played.matches(&beatmap.last_played.date())
+            && self.is_osz2.matches(beatmap.is_osz2)
+            && self
+                .beatmap_folder_name
+                .matches_str(beatmap.beatmap_folder_name)
+            && self
+                .last_checked_against_repo
+                .matches(&beatmap.last_checked_against_repo.date())
+            && self
+                .ignore_beatmap_sound
+                .matches(beatmap.ignore_beatmap_sound)
+            && self
+                .ignore_beatmap_skin
+                .matches(beatmap.ignore_beatmap_skin)
+            && self.disable_storyboard.matches(beatmap.disable_storyboard)
+            && self.disable_video.matches(beatmap.disable_video)
+            && self.visual_override.matches(beatmap.visual_override)
+            && self
+                .offset_from_song_start_in_editor_ms
+                .matches(&beatmap.offset_from_song_start_in_editor_ms)
+            && self.mania_scroll_speed.matches(&beatmap.mania_scroll_speed)
+    }
+
+    /// The command line arguments that give back these settings' filters, which is how smart
+    /// collections store their queries. Values are attached with `=` so that negative numbers
+    /// aren't mistaken for flags.
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        macro_rules! push_arg {
+            ($long:expr, $value:expr) => {
+                if let Some(value) = $value {
+                    args.push(format!("--{}={}", $long, value));
+                }
+            };
+        }
+        push_arg!("entry-size", self.entry_size.to_arg());
+        push_arg!("artist-name", self.artist_name.to_arg());
+        push_arg!("artist-name-unicode", self.artist_name_unicode.to_arg());
+        push_arg!("song-title", self.song_title.to_arg());
+        push_arg!("song-title-unicode", self.song_title_unicode.to_arg());
+        push_arg!("creator-name", self.creator_name.to_arg());
+        push_arg!("difficulty", self.difficulty.to_arg());
+        push_arg!("audio-file-name", self.audio_file_name.to_arg());
+        push_arg!("md5-beatmap-hash", self.md5_beatmap_hash.to_arg());
+        push_arg!("dotosu-file-name", self.dotosu_file_name.to_arg());
+        push_arg!("ranked-status", ranked_status_arg(self.ranked_status));
+        push_arg!("number-of-hitcircles", self.number_of_hitcircles.to_arg());
+        push_arg!("number-of-sliders", self.number_of_sliders.to_arg());
+        push_arg!("number-of-spinners", self.number_of_spinners.to_arg());
+        push_arg!(
+            "last-modification-time",
+            self.last_modification_time.to_arg()
+        );
+        push_arg!("approach-rate", self.approach_rate.to_arg());
+        push_arg!("circle-size", self.circle_size.to_arg());
+        push_arg!("hp-drain", self.hp_drain.to_arg());
+        push_arg!("overall-difficulty", self.overall_difficulty.to_arg());
+        push_arg!("slider-velocity", self.slider_velocity.to_arg());
+        push_arg!("drain-time", self.drain_time.to_arg());
+        push_arg!("total-time", self.total_time.to_arg());
+        push_arg!(
+            "preview-offset-from-start-ms",
+            self.preview_offset_from_start_ms.to_arg()
+        );
+        push_arg!("num-timing-points", self.num_timing_points.to_arg());
+        push_arg!("beatmap-id", self.beatmap_id.to_arg());
+        push_arg!("beatmap-set-id", self.beatmap_set_id.to_arg());
+        push_arg!("thread-id", self.thread_id.to_arg());
+        push_arg!("standard-grade", self.standard_grade.to_arg());
+        push_arg!("taiko-grade", self.taiko_grade.to_arg());
+        push_arg!("ctb-grade", self.ctb_grade.to_arg());
+        push_arg!("mania-grade", self.mania_grade.to_arg());
+        push_arg!("local-offset", self.local_offset.to_arg());
+        push_arg!("stack-leniency", self.stack_leniency.to_arg());
+        push_arg!("gameplay-mode", gameplay_mode_arg(self.gameplay_mode));
+        push_arg!("song-source", self.song_source.to_arg());
+        push_arg!("song-tags", self.song_tags.to_arg());
+        push_arg!("online-offset", self.online_offset.to_arg());
+        push_arg!(
+            "font-used-for-song-title",
+            self.font_used_for_song_title.to_arg()
+        );
+        push_arg!("unplayed", self.unplayed.to_arg());
+        push_arg!("last-played", self.last_played.to_arg());
+        push_arg!("is-osz2", self.is_osz2.to_arg());
+        push_arg!("beatmap-folder-name", self.beatmap_folder_name.to_arg());
+        push_arg!(
+            "last-checked-against-repo",
+            self.last_checked_against_repo.to_arg()
+        );
+        push_arg!("ignore-beatmap-sound", self.ignore_beatmap_sound.to_arg());
+        push_arg!("ignore-beatmap-skin", self.ignore_beatmap_skin.to_arg());
+        push_arg!("disable-storyboard", self.disable_storyboard.to_arg());
+        push_arg!("disable-video", self.disable_video.to_arg());
+        push_arg!("visual-override", self.visual_override.to_arg());
+        push_arg!(
+            "offset-from-song-start-in-editor-ms",
+            self.offset_from_song_start_in_editor_ms.to_arg()
+        );
+        push_arg!("mania-scroll-speed", self.mania_scroll_speed.to_arg());
+        args
+    }
+
     pub fn set_from_mask(&mut self, mask: &BeatmapMask) {
         self.entry_size.apply_mask(mask.entry_size);
         self.artist_name.apply_mask(mask.artist_name);
@@ -681,3 +847,30 @@ impl Default for BeatmapLoadSettings {
         }
     }
 }
+
+/// `RankedStatus`'s `Display` is meant for people, so this gives the spelling accepted by
+/// `--ranked-status` instead.
+fn ranked_status_arg(ranked_status: EqualCopy<RankedStatus>) -> Option<&'static str> {
+    match ranked_status {
+        EqualCopy::Eq(RankedStatus::Unknown) => Some("unknown"),
+        EqualCopy::Eq(RankedStatus::Unsubmitted) => Some("unsubmitted"),
+        EqualCopy::Eq(RankedStatus::PendingWIPGraveyard) => Some("pending"),
+        EqualCopy::Eq(RankedStatus::Unused) => Some("unused"),
+        EqualCopy::Eq(RankedStatus::Ranked) => Some("ranked"),
+        EqualCopy::Eq(RankedStatus::Approved) => Some("approved"),
+        EqualCopy::Eq(RankedStatus::Qualified) => Some("qualified"),
+        EqualCopy::Eq(RankedStatus::Loved) => Some("loved"),
+        _ => None,
+    }
+}
+
+/// Same as `ranked_status_arg`, but for `--gameplay-mode`.
+fn gameplay_mode_arg(gameplay_mode: EqualCopy<GameplayMode>) -> Option<&'static str> {
+    match gameplay_mode {
+        EqualCopy::Eq(GameplayMode::Standard) => Some("standard"),
+        EqualCopy::Eq(GameplayMode::Taiko) => Some("taiko"),
+        EqualCopy::Eq(GameplayMode::Ctb) => Some("ctb"),
+        EqualCopy::Eq(GameplayMode::Mania) => Some("mania"),
+        _ => None,
+    }
+}
@@ -1,5 +1,5 @@
-use crate::databases::osu::primitives::GameplayMode;
-use crate::load_settings::{EqualClone, EqualCopy, Relational};
+use crate::databases::{osu::primitives::GameplayMode, scores::score::Grade};
+use crate::load_settings::{EqualClone, EqualCopy, ModsFilter, Relational};
 use crate::masks::scores_mask::ScoreMask;
 use chrono::NaiveDate;
 use std::default::Default;
@@ -115,24 +115,18 @@ pub struct ScoreLoadSettings {
         name = "perfect combo",
         long = "perfect-combo",
         value_name = "EQ-BOOL",
-        possible_values(&["t", "true", "y", "yes", "1", "f", "false", "n", "no", "0"]),
+        possible_values(&["t", "true", "y", "yes", "1", "f", "false", "n", "no", "0", "ignore"]),
         default_value,
         parse(try_from_str)
     )]
     pub perfect_combo: EqualCopy<bool>,
-    #[structopt(
-        name = "mods used",
-        long = "mods-used",
-        value_name = "RELATIONAL",
-        default_value,
-        parse(try_from_str)
-    )]
-    pub mods_used: Relational<i32>,
+    #[structopt(flatten)]
+    pub mods_used: ModsFilter,
     #[structopt(skip)]
     pub empty_string: bool,
     #[structopt(
-        name = "",
-        long = "",
+        name = "replay timestamp",
+        long = "replay-timestamp",
         value_name = "RELATIONAL-DATE",
         default_value,
         parse(try_from_str)
@@ -141,13 +135,31 @@ pub struct ScoreLoadSettings {
     #[structopt(skip)]
     pub negative_one: bool,
     #[structopt(
-        name = "",
-        long = "",
+        name = "online score id",
+        long = "online-score-id",
         value_name = "RELATIONAL",
         default_value,
         parse(try_from_str)
     )]
     pub online_score_id: Relational<i64>,
+    #[structopt(
+        name = "accuracy",
+        long = "accuracy",
+        value_name = "RELATIONAL",
+        default_value,
+        parse(try_from_str),
+        help = "Accuracy in percent, e.g. '(..95)' for scores under 95%"
+    )]
+    pub accuracy: Relational<f64>,
+    #[structopt(
+        name = "grade",
+        long = "grade",
+        value_name = "EQ",
+        default_value,
+        parse(try_from_str),
+        help = "SS, SSH, S, SH, A, B, C, or D, where SSH and SH are the silver grades"
+    )]
+    pub grade: EqualCopy<Grade>,
 }
 
 impl ScoreLoadSettings {
@@ -171,6 +183,8 @@ impl ScoreLoadSettings {
             && self.replay_timestamp.is_ignore()
             && !self.negative_one
             && self.online_score_id.is_ignore()
+            && self.accuracy.is_ignore()
+            && self.grade.is_ignore()
     }
 
     pub fn is_partial(&self) -> bool {
@@ -193,6 +207,8 @@ impl ScoreLoadSettings {
             || self.replay_timestamp.is_ignore()
             || !self.negative_one
             || self.online_score_id.is_ignore()
+            || self.accuracy.is_ignore()
+            || self.grade.is_ignore()
     }
 
     pub fn set_from_mask(&mut self, mask: &ScoreMask) {
@@ -216,6 +232,23 @@ impl ScoreLoadSettings {
         self.replay_timestamp.apply_mask(mask.replay_timestamp);
         self.negative_one |= mask.negative_one;
         self.online_score_id.apply_mask(mask.online_score_id);
+        self.accuracy.apply_mask(mask.accuracy);
+        self.grade.apply_mask(mask.grade);
+        // Accuracy and grade aren't stored, so everything they're calculated from has to be loaded.
+        let grading = self.is_grading();
+        self.gameplay_mode.apply_mask(grading);
+        self.number_of_300s.apply_mask(grading);
+        self.number_of_100s.apply_mask(grading);
+        self.number_of_50s.apply_mask(grading);
+        self.number_of_gekis.apply_mask(grading);
+        self.number_of_katus.apply_mask(grading);
+        self.number_of_misses.apply_mask(grading);
+        self.mods_used.apply_mask(grading);
+    }
+
+    /// Whether the accuracy or grade has to be calculated for each score.
+    pub fn is_grading(&self) -> bool {
+        !self.accuracy.is_ignore() || !self.grade.is_ignore()
     }
 }
 
@@ -236,11 +269,13 @@ impl Default for ScoreLoadSettings {
             replay_score: Relational::default(),
             max_combo: Relational::default(),
             perfect_combo: EqualCopy::default(),
-            mods_used: Relational::default(),
+            mods_used: ModsFilter::default(),
             empty_string: bool::default(),
             replay_timestamp: Relational::default(),
             negative_one: bool::default(),
             online_score_id: Relational::default(),
+            accuracy: Relational::default(),
+            grade: EqualCopy::default(),
         }
     }
 }
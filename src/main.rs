@@ -5,11 +5,12 @@ mod interactive;
 mod load_settings;
 mod masks;
 mod maybe_deserialize_primitives;
+mod output;
 mod read_error;
 mod serialize_primitives;
 
 use argument::*;
-use databases::database::OsuDatabase;
+use databases::{collection_command::read_osu_db, database::OsuDatabase, osu::osudb::OsuDb};
 use load_settings::LoadSettings;
 use masks::DbMask;
 use std::fs::File;
@@ -22,6 +23,7 @@ fn main() {
         db_type,
         db_path,
         jobs,
+        osu_db_path,
         merge_search_or_interface,
     } = Arguments::from_args();
     let timer = Instant::now();
@@ -31,8 +33,32 @@ fn main() {
         .expect("Failed to read database file.");
     let (database, mask, interface) = if let Some(msi) = merge_search_or_interface {
         match msi {
-            MergeSearchOrInterface::Merge(_merge) => {
-                println!("Merging not currently supported.");
+            MergeSearchOrInterface::Merge(merge) => {
+                merge.merge(jobs, db_type, &buffer);
+                return;
+            }
+            MergeSearchOrInterface::Diff(diff) => {
+                diff.diff(jobs, db_type, &buffer);
+                return;
+            }
+            MergeSearchOrInterface::Convert(convert) => {
+                convert.convert(jobs, db_type, &buffer);
+                return;
+            }
+            MergeSearchOrInterface::Collection(collection) => {
+                collection.run(jobs, db_type, &db_path, &buffer);
+                return;
+            }
+            MergeSearchOrInterface::Remap(remap) => {
+                remap.remap(jobs, db_type, &buffer);
+                return;
+            }
+            MergeSearchOrInterface::Random(random) => {
+                random.pick(jobs, db_type, &buffer);
+                return;
+            }
+            MergeSearchOrInterface::Playlist(playlist) => {
+                playlist.generate(jobs, db_type, &buffer);
                 return;
             }
             MergeSearchOrInterface::Search { search } => match search {
@@ -98,8 +124,20 @@ fn main() {
     };
     let elapsed = timer.elapsed();
     println!("Successfully loaded database! Time taken: {:?}", elapsed);
+    let osu_buffer = match (&osu_db_path, db_type) {
+        (Some(path), DbIndicator::CollectionDb) => Some(read_osu_db(path)),
+        (Some(_), _) => {
+            println!("--osu-db is only used when displaying collection.db, ignoring it.");
+            None
+        }
+        (None, _) => None,
+    };
+    let osu_db = osu_buffer
+        .as_ref()
+        .map(|buffer| OsuDb::read_from_bytes(jobs, buffer).expect("Failed to parse osu!.db."));
+    let beatmaps = osu_db.as_ref().map(|osu_db| osu_db.beatmaps_by_hash());
     match interface {
-        InterfaceType::None => database.display(mask),
+        InterfaceType::None => database.display(mask, beatmaps.as_ref()),
         _ => println!("Interface {:?} not yet supported.", interface),
     }
 }
@@ -44,6 +44,10 @@ pub struct ScoreMask {
     pub negative_one: bool,
     #[structopt(name = "show-online-score-id", long = "show-online-score-id")]
     pub online_score_id: bool,
+    #[structopt(name = "show-accuracy", long = "show-accuracy")]
+    pub accuracy: bool,
+    #[structopt(name = "show-grade", long = "show-grade")]
+    pub grade: bool,
 }
 
 impl ScoreMask {
@@ -67,6 +71,8 @@ impl ScoreMask {
             && !self.replay_timestamp
             && !self.negative_one
             && !self.online_score_id
+            && !self.accuracy
+            && !self.grade
     }
 }
 
@@ -2,9 +2,8 @@ use crate::databases::osu::primitives::ByteSingle;
 use crate::deserialize_primitives::*;
 use crate::load_settings::{EqualClone, EqualCopy, Relational};
 use crate::read_error::{DbFileParseError, ParseErrorKind::*, ParseFileResult};
-use chrono::{naive::NaiveDate, Duration as ChronoDuration};
+use chrono::naive::NaiveDate;
 use std::str;
-use std::time::Duration;
 
 // Primitive types we need to read from databases:
 // Byte
@@ -459,18 +458,9 @@ pub fn maybe_read_datetime(
         } else {
             let mut buf = [0; 8];
             buf.copy_from_slice(&bytes[*i..*i + 8]);
-            let ticks = u64::from_le_bytes(buf);
+            let ticks = i64::from_le_bytes(buf);
             *i += 8;
-            let duration_since_epoch = Duration::from_micros(ticks / 10);
-            let chrono_duration = ChronoDuration::from_std(duration_since_epoch).map_err(|e| {
-                let msg = format!(
-                    "Failed to convert std::time::Duration to chrono::Duration\n\
-                     {}",
-                    e
-                );
-                DbFileParseError::new(PrimitiveError, msg)
-            })?;
-            let naive_date = NaiveDate::from_ymd(1970, 0, 0) + chrono_duration;
+            let naive_date = datetime_from_ticks(ticks).date();
             if s.compare(&naive_date) {
                 Ok(Some(naive_date))
             } else {
@@ -497,18 +487,9 @@ pub fn maybe_read_datetime_nocomp(
         } else {
             let mut buf = [0; 8];
             buf.copy_from_slice(&bytes[*i..*i + 8]);
-            let ticks = u64::from_le_bytes(buf);
+            let ticks = i64::from_le_bytes(buf);
             *i += 8;
-            let duration_since_epoch = Duration::from_micros(ticks / 10);
-            let chrono_duration = ChronoDuration::from_std(duration_since_epoch).map_err(|e| {
-                let msg = format!(
-                    "Failed to convert std::time::Duration to chrono::Duration\n\
-                     {}",
-                    e
-                );
-                DbFileParseError::new(PrimitiveError, msg)
-            })?;
-            let naive_date = NaiveDate::from_ymd(1970, 0, 0) + chrono_duration;
+            let naive_date = datetime_from_ticks(ticks).date();
             Ok(Some(naive_date))
         }
     } else {
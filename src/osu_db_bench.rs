@@ -1,4 +1,4 @@
-#![allow(dead_code)]
+#![allow(dead_code, unused_imports)]
 
 mod argument;
 mod databases;
@@ -6,7 +6,9 @@ mod deserialize_primitives;
 mod load_settings;
 mod masks;
 mod maybe_deserialize_primitives;
+mod output;
 mod read_error;
+mod serialize_primitives;
 
 use crate::databases::{osu::osudb::OsuDb, scores::scoresdb::ScoresDb};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
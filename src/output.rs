@@ -0,0 +1,120 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use structopt::StructOpt;
+
+// Options shared by every command that writes a database to a new file. This is a plain comment
+// because structopt would otherwise use it as the about text of every command flattening it in.
+#[derive(StructOpt)]
+pub struct Output {
+    #[structopt(short = "o", long = "output", value_name = "PATH")]
+    pub path: String,
+    #[structopt(long = "overwrite-output")]
+    pub overwrite: bool,
+    #[structopt(
+        name = "backups",
+        long = "backups",
+        value_name = "NUM",
+        default_value = "3",
+        help = "Number of rotating PATH.bak.N copies to keep of a file before it's overwritten."
+    )]
+    pub backups: usize,
+}
+
+// How many backups to keep of collection.db when a command saves its changes back to it, shared
+// by every such command.
+#[derive(StructOpt)]
+pub struct Backups {
+    #[structopt(
+        name = "backups",
+        long = "backups",
+        value_name = "NUM",
+        default_value = "3",
+        help = "Number of rotating PATH.bak.N copies to keep of collection.db before it's changed."
+    )]
+    pub count: usize,
+}
+
+impl Output {
+    /// Check whether writing to the output path is allowed, explaining why not if it isn't.
+    pub fn can_write(&self) -> bool {
+        if !self.overwrite && Path::new(&self.path).exists() {
+            println!(
+                "Output file {} already exists. Use --overwrite-output to overwrite it.",
+                self.path
+            );
+            false
+        } else {
+            true
+        }
+    }
+
+    pub fn write(&self, bytes: &[u8]) -> io::Result<()> {
+        write_atomically(Path::new(&self.path), bytes, self.backups)
+    }
+}
+
+/// Write `bytes` to `path` such that `path` always holds either the old or the new file in full,
+/// even if the process dies partway through. The bytes are written to a temporary file next to
+/// `path`, synced to disk, and then renamed over `path`. If `path` already exists, it's first
+/// copied to `path.bak.1`, shifting older backups up to `path.bak.{backups}`, past which they're
+/// removed.
+pub fn write_atomically(path: &Path, bytes: &[u8], backups: usize) -> io::Result<()> {
+    if backups > 0 && path.exists() {
+        rotate_backups(path, backups)?;
+    }
+    let temp_path = suffixed(path, &format!(".tmp.{}", process::id()));
+    let result = write_and_sync(&temp_path, bytes).and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+        return result;
+    }
+    sync_parent_dir(path)
+}
+
+fn write_and_sync(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+fn rotate_backups(path: &Path, backups: usize) -> io::Result<()> {
+    let backup = |n: usize| suffixed(path, &format!(".bak.{}", n));
+    let oldest = backup(backups);
+    if oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for n in (1..backups).rev() {
+        let newer = backup(n);
+        if newer.exists() {
+            fs::rename(&newer, backup(n + 1))?;
+        }
+    }
+    // Copy rather than rename, so that `path` never goes missing.
+    let newest = backup(1);
+    fs::copy(path, &newest)?;
+    fs::OpenOptions::new().write(true).open(&newest)?.sync_all()
+}
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Make the rename itself durable. Directories can't be opened as files on Windows, where this is
+/// skipped.
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => File::open(parent)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
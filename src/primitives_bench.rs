@@ -3,11 +3,8 @@
 mod deserialize_primitives;
 mod read_error;
 
-use chrono::{naive::NaiveDate, Duration as ChronoDuration};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use rand::{thread_rng, Rng};
-use read_error::{DbFileParseError, ParseErrorKind::PrimitiveError};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use deserialize_primitives::*;
 
@@ -139,28 +136,10 @@ fn bench_read_boolean(c: &mut Criterion) {
 fn bench_read_datetime(c: &mut Criterion) {
     c.bench_function("Read datetime", move |b| {
         let mut rng = thread_rng();
-        let offset_100_nanosecs = rng.gen::<u64>();
-        let duration = Duration::from_secs(offset_100_nanosecs / 10_000_000);
-        let chrono_duration = ChronoDuration::from_std(duration).map_err(|e| {
-            let msg = format!(
-                "Failed to convert std::time::Duration to chrono::Duration\n\
-             {}",
-                e
-            );
-            DbFileParseError::new(PrimitiveError, msg)
-        });
-        let chrono_duration = match chrono_duration {
-            Ok(chrono_duration) => chrono_duration,
-            Err(ref e) => {
-                println!(
-                    "Tried to benchmark invalid datetime with offset of {:?}.\n{:?}",
-                    chrono_duration, e
-                );
-                return;
-            }
-        };
-        let datetime = NaiveDate::from_ymd(1970, 1, 1) + chrono_duration;
-        let bytes = u64::to_le_bytes(offset_100_nanosecs);
+        // .NET `DateTime`s can't go past the end of the year 9999.
+        let ticks = rng.gen_range(0, 3_155_378_975_999_999_999i64);
+        let datetime = datetime_from_ticks(ticks);
+        let bytes = i64::to_le_bytes(ticks);
         b.iter(|| {
             assert!(Ok(datetime) == read_datetime(&bytes, &mut 0));
         });
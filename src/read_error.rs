@@ -4,6 +4,7 @@ pub enum ParseErrorKind {
     OsuDbError,
     CollectionDbError,
     ScoresDbError,
+    OsdbError,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
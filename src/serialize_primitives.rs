@@ -1 +1,276 @@
+use chrono::naive::NaiveDateTime;
 
+use crate::deserialize_primitives::dotnet_epoch;
+// Primitive types we need to write to databases:
+// Byte
+// Short
+// Int
+// Long
+// ULEB128
+// Single
+// Double
+// Boolean
+// String
+// Datetime
+// Every writer here is the exact inverse of its `read_*` counterpart in deserialize_primitives.rs.
+
+#[inline]
+pub fn write_byte(byte: u8, bytes: &mut Vec<u8>) {
+    bytes.push(byte);
+}
+
+#[inline]
+pub fn write_short(short: i16, bytes: &mut Vec<u8>) {
+    bytes.extend_from_slice(&short.to_le_bytes());
+}
+
+#[inline]
+pub fn write_int(int: i32, bytes: &mut Vec<u8>) {
+    bytes.extend_from_slice(&int.to_le_bytes());
+}
+
+#[inline]
+pub fn write_long(long: i64, bytes: &mut Vec<u8>) {
+    bytes.extend_from_slice(&long.to_le_bytes());
+}
+
+#[inline]
+pub fn write_uleb128(mut uleb128: usize, bytes: &mut Vec<u8>) {
+    loop {
+        let b = (uleb128 & 0b01111111) as u8;
+        uleb128 >>= 7;
+        if uleb128 == 0 {
+            bytes.push(b);
+            break;
+        } else {
+            bytes.push(b | 0b10000000);
+        }
+    }
+}
+
+#[inline]
+pub fn write_single(single: f32, bytes: &mut Vec<u8>) {
+    write_int(single.to_bits() as i32, bytes);
+}
+
+#[inline]
+pub fn write_double(double: f64, bytes: &mut Vec<u8>) {
+    write_long(double.to_bits() as i64, bytes);
+}
+
+#[inline]
+pub fn write_boolean(boolean: bool, bytes: &mut Vec<u8>) {
+    write_byte(boolean as u8, bytes);
+}
+
+/// Strings are written as a `0x00` byte if they're absent, or as a `0x0b` byte followed by the
+/// ULEB128-encoded length of the string and then the UTF-8 bytes of the string itself.
+#[inline]
+pub fn write_str_utf8(string: Option<&str>, bytes: &mut Vec<u8>) {
+    if let Some(string) = string {
+        bytes.push(0x0b);
+        write_uleb128(string.len(), bytes);
+        bytes.extend_from_slice(string.as_bytes());
+    } else {
+        bytes.push(0);
+    }
+}
+
+#[inline]
+pub fn write_datetime(datetime: NaiveDateTime, bytes: &mut Vec<u8>) {
+    write_long(ticks_from_datetime(datetime), bytes);
+}
+
+/// Inverse of `datetime_from_ticks`.
+#[inline]
+pub fn ticks_from_datetime(datetime: NaiveDateTime) -> i64 {
+    let since_epoch = datetime - dotnet_epoch();
+    since_epoch.num_seconds() * 10_000_000 + since_epoch.subsec_nanos() as i64 / 100
+}
+
+/// `read_md5_hash` reads an empty hash from a `0x00` indicator, so an empty hash is written back
+/// the same way. Any other hash is written like any other string, and has to be 32 bytes long,
+/// since that's how many `read_md5_hash` reads.
+#[inline]
+pub fn write_md5_hash(hash: &str, bytes: &mut Vec<u8>) {
+    debug_assert!(
+        hash.is_empty() || hash.len() == 32,
+        "MD5 hash must be empty or 32 bytes long: {:?}",
+        hash
+    );
+    if hash.is_empty() {
+        bytes.push(0);
+    } else {
+        write_str_utf8(Some(hash), bytes);
+    }
+}
+
+/// Player names are under 64 characters long, so their length is always a single ULEB128 byte,
+/// which is what `read_player_name` expects.
+#[inline]
+pub fn write_player_name(player_name: Option<&str>, bytes: &mut Vec<u8>) {
+    write_str_utf8(player_name, bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deserialize_primitives::*;
+    use crate::read_error::ParseFileResult;
+
+    /// Write `value`, read it back, and check the reader consumed exactly what was written.
+    fn round_trip<T>(
+        value: T,
+        write: impl Fn(T, &mut Vec<u8>),
+        read: impl Fn(&[u8], &mut usize) -> ParseFileResult<T>,
+    ) -> T {
+        let mut bytes = Vec::new();
+        write(value, &mut bytes);
+        let mut i = 0;
+        let read_back = read(&bytes, &mut i).expect("Failed to read back written value.");
+        assert_eq!(i, bytes.len());
+        read_back
+    }
+
+    #[test]
+    fn integers_round_trip() {
+        for &byte in &[0, 1, 0x7f, 0x80, 0xff] {
+            assert_eq!(round_trip(byte, write_byte, read_byte), byte);
+        }
+        for &short in &[0, 1, -1, i16::MIN, i16::MAX] {
+            assert_eq!(round_trip(short, write_short, read_short), short);
+        }
+        for &int in &[0, 1, -1, 20211231, i32::MIN, i32::MAX] {
+            assert_eq!(round_trip(int, write_int, read_int), int);
+        }
+        for &long in &[0, 1, -1, 637_000_000_000_000_000, i64::MIN, i64::MAX] {
+            assert_eq!(round_trip(long, write_long, read_long), long);
+        }
+        for &boolean in &[false, true] {
+            assert_eq!(round_trip(boolean, write_boolean, read_boolean), boolean);
+        }
+    }
+
+    #[test]
+    fn uleb128_round_trips() {
+        for &(value, len) in &[(0, 1), (127, 1), (128, 2), (16383, 2), (16384, 3)] {
+            let mut bytes = Vec::new();
+            write_uleb128(value, &mut bytes);
+            assert_eq!(bytes.len(), len, "{}", value);
+            assert_eq!(round_trip(value, write_uleb128, read_uleb128), value);
+        }
+        for &value in &[usize::MAX >> 1, usize::MAX] {
+            assert_eq!(round_trip(value, write_uleb128, read_uleb128), value);
+        }
+    }
+
+    #[test]
+    fn uleb128_wider_than_usize_is_rejected() {
+        let mut bytes = Vec::new();
+        write_uleb128(usize::MAX, &mut bytes);
+        // One more bit than fits.
+        *bytes.last_mut().unwrap() <<= 1;
+        assert!(read_uleb128(&bytes, &mut 0).is_err());
+        // One more byte than fits.
+        *bytes.last_mut().unwrap() |= 0b10000000;
+        bytes.push(0);
+        assert!(read_uleb128(&bytes, &mut 0).is_err());
+    }
+
+    #[test]
+    fn floats_round_trip_bit_for_bit() {
+        let singles = [0.0, -0.0, 1.5, -9.25, f32::MIN_POSITIVE, f32::INFINITY];
+        let single_nans = [f32::NAN.to_bits(), 0x7fc0_0001, 0xffc0_0000, 0x7f80_0001];
+        for bits in singles
+            .iter()
+            .map(|s| s.to_bits())
+            .chain(single_nans.iter().copied())
+        {
+            let single = round_trip(f32::from_bits(bits), write_single, read_single);
+            assert_eq!(single.to_bits(), bits);
+        }
+        let doubles = [0.0, -0.0, 1.5, -9.25, f64::MIN_POSITIVE, f64::NEG_INFINITY];
+        let double_nans = [
+            f64::NAN.to_bits(),
+            0x7ff8_0000_0000_0001,
+            0xfff8_0000_0000_0000,
+            0x7ff0_0000_0000_0001,
+        ];
+        for bits in doubles
+            .iter()
+            .map(|d| d.to_bits())
+            .chain(double_nans.iter().copied())
+        {
+            let double = round_trip(f64::from_bits(bits), write_double, read_double);
+            assert_eq!(double.to_bits(), bits);
+        }
+    }
+
+    #[test]
+    fn strings_round_trip() {
+        let read = |bytes: &[u8], i: &mut usize| {
+            read_str_utf8(bytes, i, "test").map(|s| s.map(str::to_string))
+        };
+        let long = "x".repeat(200);
+        for string in &[
+            None,
+            Some(""),
+            Some("osu!"),
+            Some("ノート"),
+            Some(long.as_str()),
+        ] {
+            let mut bytes = Vec::new();
+            write_str_utf8(*string, &mut bytes);
+            assert_eq!(bytes[0], if string.is_some() { 0x0b } else { 0x00 });
+            let mut i = 0;
+            assert_eq!(read(&bytes, &mut i).unwrap().as_deref(), *string);
+            assert_eq!(i, bytes.len());
+        }
+    }
+
+    #[test]
+    fn md5_hashes_round_trip() {
+        for hash in &["", "d41d8cd98f00b204e9800998ecf8427e"] {
+            let mut bytes = Vec::new();
+            write_md5_hash(hash, &mut bytes);
+            assert_eq!(bytes.len(), if hash.is_empty() { 1 } else { 34 });
+            let mut i = 0;
+            assert_eq!(read_md5_hash(&bytes, &mut i).unwrap(), *hash);
+            assert_eq!(i, bytes.len());
+        }
+    }
+
+    #[test]
+    fn player_names_round_trip() {
+        let longest = "x".repeat(63);
+        for name in &[None, Some(""), Some("peppy"), Some(longest.as_str())] {
+            let mut bytes = Vec::new();
+            write_player_name(*name, &mut bytes);
+            let mut i = 0;
+            assert_eq!(read_player_name(&bytes, &mut i).unwrap(), *name);
+            assert_eq!(i, bytes.len());
+        }
+    }
+
+    #[test]
+    fn ticks_round_trip_through_datetimes() {
+        for &ticks in &[
+            -1,
+            -10_000_001,
+            -637_000_000_000_000_000,
+            0,
+            1,
+            9_999_999,
+            10_000_001,
+            637_000_000_123_456_789,
+            3_155_378_975_999_999_999,
+        ] {
+            assert_eq!(ticks_from_datetime(datetime_from_ticks(ticks)), ticks);
+            let datetime = datetime_from_ticks(ticks);
+            assert_eq!(
+                round_trip(datetime, write_datetime, read_datetime),
+                datetime
+            );
+        }
+    }
+}